@@ -1,5 +1,17 @@
-use crate::transaction::{Transaction, TransactionError};
-use sha2::{Digest, Sha256};
+use crate::encoding::{encode_amount, encode_timestamp, encode_u64, to_hex};
+use crate::hash::{ChainHasher, Hasher};
+use crate::merkle::{merkle_proof, merkle_root};
+use crate::transaction::{Address, Transaction, TransactionError};
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+#[cfg(feature = "tokio")]
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use time::OffsetDateTime;
 
@@ -7,6 +19,8 @@ use time::OffsetDateTime;
 pub enum BlockError {
     #[error("Invalid block hash")]
     InvalidHash,
+    #[error("Merkle root does not match the transactions")]
+    InvalidMerkleRoot,
     #[error("Invalid previous hash format")]
     InvalidPreviousHash,
     #[error("Proof of work validation failed")]
@@ -15,97 +29,447 @@ pub enum BlockError {
     InvalidDifficulty,
     #[error("No transactions in block")]
     EmptyTransactions,
+    #[error("Block encodes to more bytes than allowed")]
+    BlockTooLarge,
+    #[error("Block has more transactions than allowed")]
+    TooManyTransactions,
+    #[error("Block spends the same sender nonce twice")]
+    DuplicateNonce,
+    #[error("Only the first transaction may be a coinbase")]
+    MisplacedCoinbase,
+    #[error("Coinbase pays more than the allowed reward")]
+    ExcessiveCoinbase,
     #[error("Transaction error: {0}")]
     TransactionError(#[from] TransactionError),
+    #[error("Malformed block encoding: {0}")]
+    DecodingError(&'static str),
 }
 
+/// Everything the block hash commits to. Transactions are covered through
+/// the Merkle root, so the header alone is enough to check proof of work
 #[derive(Debug, Clone, PartialEq)]
-pub struct Block {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockHeader {
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::encoding::serde_unix_timestamp")
+    )]
     timestamp: OffsetDateTime,
-    transactions: Vec<Transaction>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hash"))]
     previous_hash: [u8; 32],
-    hash: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hash"))]
+    merkle_root: [u8; 32], // Commits to the transactions in the block hash
     nonce: u64,
     difficulty: u32, // Number of leading zeros required
 }
 
+impl BlockHeader {
+    /// Calculates the hash of the header
+    pub fn calculate_hash(&self) -> [u8; 32] {
+        Self::hash_with_nonce(&self.hash_prefix(), self.nonce)
+    }
+
+    /// The hashed bytes with the nonce left zeroed. The nonce goes last, so
+    /// mining builds this once and only rewrites the tail per attempt
+    fn hash_prefix(&self) -> [u8; HEADER_HASH_INPUT_LEN] {
+        let mut input = [0; HEADER_HASH_INPUT_LEN];
+        input[..8].copy_from_slice(&encode_timestamp(self.timestamp));
+        input[8..40].copy_from_slice(&self.previous_hash);
+        input[40..72].copy_from_slice(&self.merkle_root);
+        input[72..76].copy_from_slice(&self.difficulty.to_be_bytes());
+        input
+    }
+
+    fn hash_with_nonce(prefix: &[u8; HEADER_HASH_INPUT_LEN], nonce: u64) -> [u8; 32] {
+        let mut input = *prefix;
+        input[76..].copy_from_slice(&encode_u64(nonce));
+        ChainHasher::hash(&input)
+    }
+
+    pub fn timestamp(&self) -> OffsetDateTime {
+        self.timestamp
+    }
+
+    pub fn previous_hash(&self) -> [u8; 32] {
+        self.previous_hash
+    }
+
+    pub fn merkle_root(&self) -> [u8; 32] {
+        self.merkle_root
+    }
+
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    pub fn difficulty(&self) -> u32 {
+        self.difficulty
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Block {
+    header: BlockHeader,
+    transactions: Vec<Transaction>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hash"))]
+    hash: [u8; 32], // Hash of the header, kept so it is not recomputed on every lookup
+    // Set once mined; a sealed block is never changed again. Only mined
+    // blocks are persisted or relayed, so deserialized blocks come back sealed
+    #[cfg_attr(feature = "serde", serde(skip_serializing, default = "sealed_on_load"))]
+    sealed: bool,
+}
+
+#[cfg(feature = "serde")]
+fn sealed_on_load() -> bool {
+    true
+}
+
+/// Nonces tried between checks for cancellation
+const MINING_POLL_INTERVAL: u64 = 1024;
+
+/// Sets its flag when dropped, so a dropped `mine_async` future stops its
+/// worker
+#[cfg(feature = "tokio")]
+struct StopOnDrop(Arc<AtomicBool>);
+
+#[cfg(feature = "tokio")]
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, AtomicOrdering::Relaxed);
+    }
+}
+
+// Bytes the header hash covers: timestamp, previous hash, Merkle root,
+// difficulty and nonce
+const HEADER_HASH_INPUT_LEN: usize = 8 + 32 + 32 + 4 + 8;
+
+// Encoded size of an unsigned transaction with no data, without and with a
+// salt
+const TX_ENCODED_MIN: usize = 20 + 20 + 8 + 8 + 1 + 4 + 1 + 1;
+const TX_ENCODED_MAX: usize = TX_ENCODED_MIN + 8;
+
+// Encoded size of everything but the transactions: timestamp, count,
+// Merkle root, previous hash, hash, nonce and difficulty
+const BLOCK_ENCODED_FIXED: usize = 8 + 4 + 32 + 32 + 32 + 8 + 4;
+
+// Encoded size of bytes written by `put_optional_bytes`
+fn optional_bytes_len(bytes: Option<&[u8]>) -> usize {
+    bytes.map_or(0, |bytes| 4 + bytes.len())
+}
+
+// Writes a presence flag byte, then a `u32` length and the bytes if present
+fn put_optional_bytes(out: &mut Vec<u8>, bytes: Option<&[u8]>) {
+    match bytes {
+        Some(bytes) => {
+            out.push(1);
+            out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            out.extend_from_slice(bytes);
+        }
+        None => out.push(0),
+    }
+}
+
+// Cursor over the input of `Block::from_bytes`
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], BlockError> {
+        if self.0.len() < N {
+            return Err(BlockError::DecodingError("truncated input"));
+        }
+        let (head, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn take_optional(&mut self) -> Result<Option<&'a [u8]>, BlockError> {
+        match self.take::<1>()? {
+            [0] => Ok(None),
+            [1] => {
+                let len = u32::from_be_bytes(self.take()?) as usize;
+                self.take_slice(len).map(Some)
+            }
+            _ => Err(BlockError::DecodingError("invalid presence flag")),
+        }
+    }
+
+    fn take_slice(&mut self, len: usize) -> Result<&'a [u8], BlockError> {
+        if self.0.len() < len {
+            return Err(BlockError::DecodingError("truncated input"));
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+}
+
 impl Block {
-    /// Creates a new block with the given data and previous hash
+    /// Creates a new block with the given data and previous hash, stamped
+    /// with the current time
+    #[cfg(feature = "std")]
     pub fn new(
         transactions: Vec<Transaction>,
         previous_hash: [u8; 32],
         difficulty: u32,
+    ) -> Result<Self, BlockError> {
+        Self::new_at(
+            transactions,
+            previous_hash,
+            difficulty,
+            OffsetDateTime::now_utc(),
+        )
+    }
+
+    /// Creates a new block stamped `timestamp` instead of the current time,
+    /// e.g. for a genesis block every node must build identically
+    pub fn new_at(
+        transactions: Vec<Transaction>,
+        previous_hash: [u8; 32],
+        difficulty: u32,
+        timestamp: OffsetDateTime,
     ) -> Result<Self, BlockError> {
         if transactions.is_empty() {
             return Err(BlockError::EmptyTransactions);
         }
 
-        let timestamp = OffsetDateTime::now_utc();
-        let mut block = Self {
+        // Whole seconds only: that is what the hash commits to
+        let timestamp = timestamp.replace_nanosecond(0).unwrap_or(timestamp);
+        let header = BlockHeader {
             timestamp,
-            transactions,
             previous_hash,
-            hash: [0; 32],
+            merkle_root: Self::compute_merkle_root(&transactions),
             nonce: 0,
             difficulty,
         };
+        let mut block = Self {
+            header,
+            transactions,
+            hash: [0; 32],
+            sealed: false,
+        };
+        block.hash = block.calculate_hash();
+        Ok(block)
+    }
+
+    /// Rebuilds an already mined block from its stored parts. Only the hash
+    /// is recomputed; the nonce is trusted as given, so call `verify` to
+    /// confirm the proof of work
+    pub fn from_mined_parts(
+        transactions: Vec<Transaction>,
+        previous_hash: [u8; 32],
+        difficulty: u32,
+        timestamp: OffsetDateTime,
+        nonce: u64,
+    ) -> Result<Self, BlockError> {
+        if transactions.is_empty() {
+            return Err(BlockError::EmptyTransactions);
+        }
+
+        let header = BlockHeader {
+            timestamp,
+            previous_hash,
+            merkle_root: Self::compute_merkle_root(&transactions),
+            nonce,
+            difficulty,
+        };
+        let mut block = Self {
+            header,
+            transactions,
+            hash: [0; 32],
+            sealed: true,
+        };
         block.hash = block.calculate_hash();
         Ok(block)
     }
 
-    /// Calculates the hash of the block based on its contents
+    /// Calculates the hash of the block, which is the hash of its header
     pub fn calculate_hash(&self) -> [u8; 32] {
-        let mut hasher = Sha256::new();
+        self.header.calculate_hash()
+    }
 
-        // Hash timestamp
-        hasher.update(self.timestamp.unix_timestamp().to_be_bytes());
+    /// Merkle root over the hashes of `transactions`
+    pub fn compute_merkle_root(transactions: &[Transaction]) -> [u8; 32] {
+        let leaves: Vec<[u8; 32]> = transactions.iter().map(|tx| tx.hash()).collect();
+        merkle_root(&leaves)
+    }
 
-        // Hash all transactions
-        for transaction in &self.transactions {
-            hasher.update(transaction.sender().as_bytes());
-            hasher.update(transaction.receiver().as_bytes());
-            hasher.update(transaction.amount().to_be_bytes());
-            hasher.update(transaction.nonce().to_be_bytes());
+    /// Proof that the transaction at `tx_index` is under `merkle_root()`,
+    /// checked with `merkle::verify_merkle_proof`
+    pub fn merkle_proof(&self, tx_index: usize) -> Option<Vec<([u8; 32], bool)>> {
+        let leaves: Vec<[u8; 32]> = self.transactions.iter().map(|tx| tx.hash()).collect();
+        merkle_proof(&leaves, tx_index)
+    }
+
+    /// Searches for a nonce satisfying the difficulty and seals the block.
+    /// Mining an already sealed block leaves it untouched
+    pub fn mine(&mut self) {
+        self.mine_while(|| true);
+    }
+
+    /// Mines until a nonce is found or `stop` is set. Returns whether the
+    /// block is sealed; a cancelled search resumes where it left off
+    pub fn mine_with_cancel(&mut self, stop: &AtomicBool) -> bool {
+        self.mine_while(|| !stop.load(AtomicOrdering::Relaxed))
+    }
+
+    /// Mines on tokio's blocking pool so the calling task does not hold up
+    /// its executor. Dropping the future stops the search; the block is then
+    /// left as it was
+    #[cfg(feature = "tokio")]
+    pub async fn mine_async(&mut self) {
+        if self.sealed {
+            return;
         }
+        let stop = Arc::new(AtomicBool::new(false));
+        let _stop_on_drop = StopOnDrop(Arc::clone(&stop));
+
+        let mut block = self.clone();
+        let mined = tokio::task::spawn_blocking(move || {
+            block.mine_with_cancel(&stop);
+            block
+        })
+        .await
+        .expect("mining task panicked");
+        *self = mined;
+    }
 
-        // Hash previous hash
-        hasher.update(self.previous_hash);
+    /// Mines for at most roughly `duration`. Returns whether the block is sealed
+    #[cfg(feature = "std")]
+    pub fn mine_with_timeout(&mut self, duration: Duration) -> bool {
+        let started = Instant::now();
+        self.mine_while(|| started.elapsed() < duration)
+    }
 
-        // Hash nonce
-        hasher.update(self.nonce.to_be_bytes());
+    /// Mines on `threads` threads: worker `i` tries nonces `nonce + i`,
+    /// `nonce + i + threads`, ... and all stop as soon as one finds a proof.
+    /// Which valid nonce wins depends on thread scheduling
+    #[cfg(feature = "std")]
+    pub fn mine_parallel(&mut self, threads: usize) {
+        if self.sealed {
+            return;
+        }
+        let threads = threads.max(1) as u64;
+        let target = Self::difficulty_target(self.header.difficulty);
+        let prefix = self.header.hash_prefix();
+        let start = self.header.nonce;
+        let found = AtomicBool::new(false);
+        let winner = Mutex::new(None);
 
-        hasher.finalize().into()
+        std::thread::scope(|scope| {
+            for offset in 0..threads {
+                let (prefix, found, winner) = (&prefix, &found, &winner);
+                scope.spawn(move || {
+                    let mut nonce = start.wrapping_add(offset);
+                    for attempt in 0u64.. {
+                        if attempt % MINING_POLL_INTERVAL == 0
+                            && found.load(AtomicOrdering::Relaxed)
+                        {
+                            return;
+                        }
+
+                        let hash = BlockHeader::hash_with_nonce(prefix, nonce);
+                        if hash <= target {
+                            if !found.swap(true, AtomicOrdering::Relaxed) {
+                                *winner.lock().unwrap() = Some((nonce, hash));
+                            }
+                            return;
+                        }
+                        nonce = nonce.wrapping_add(threads);
+                    }
+                });
+            }
+        });
+
+        let (nonce, hash) = winner
+            .into_inner()
+            .unwrap()
+            .expect("workers only stop once a proof is found");
+        self.header.nonce = nonce;
+        self.hash = hash;
+        self.sealed = true;
     }
 
-    pub fn mine(&mut self) {
-        let target = 0u64.wrapping_sub(1) >> self.difficulty;
-        loop {
-            let hash = self.calculate_hash();
-            // Convert first 8 bytes of hash to u64 for easy comparison
-            let hash_num = u64::from_be_bytes(hash[0..8].try_into().unwrap());
-            if hash_num <= target {
+    /// Tries nonces while `keep_going` holds, polling it every
+    /// `MINING_POLL_INTERVAL` attempts
+    fn mine_while(&mut self, mut keep_going: impl FnMut() -> bool) -> bool {
+        if self.sealed {
+            return true;
+        }
+
+        let target = Self::difficulty_target(self.header.difficulty);
+        let prefix = self.header.hash_prefix();
+        for attempt in 0u64.. {
+            if attempt % MINING_POLL_INTERVAL == 0 && !keep_going() {
+                return false;
+            }
+
+            let hash = BlockHeader::hash_with_nonce(&prefix, self.header.nonce);
+            if hash <= target {
                 self.hash = hash;
+                self.sealed = true;
                 break;
             }
-            self.nonce = self.nonce.wrapping_add(1);
+            self.header.nonce = self.header.nonce.wrapping_add(1);
+        }
+        true
+    }
+
+    /// The largest hash, read as a big-endian 256-bit number, that meets
+    /// `difficulty`: `difficulty` leading zero bits followed by all ones.
+    /// Difficulty 256 and above only admits the all-zero hash
+    pub fn difficulty_target(difficulty: u32) -> [u8; 32] {
+        let mut target = [0xff; 32];
+        let zero_bytes = (difficulty / 8).min(32) as usize;
+        target[..zero_bytes].fill(0);
+        if let Some(partial) = target.get_mut(zero_bytes) {
+            *partial = 0xff >> (difficulty % 8);
         }
+        target
     }
 
+    /// Byte arrays compare lexicographically, which for big-endian numbers
+    /// is numeric order
     pub fn has_valid_proof(&self) -> bool {
-        let hash_num = u64::from_be_bytes(self.hash[0..8].try_into().unwrap());
-        let target = 0u64.wrapping_sub(1) >> self.difficulty;
-        hash_num <= target
+        self.hash <= Self::difficulty_target(self.header.difficulty)
     }
 
     pub fn verify(&self, is_genesis: bool) -> Result<(), BlockError> {
+        self.verify_with_threads(is_genesis, 1)
+    }
+
+    /// Like `verify`, but validates transactions (and so checks signatures)
+    /// on up to `threads` threads. The result does not depend on the thread
+    /// count: a failure is always reported for the lowest failing index.
+    /// Without the `std` feature everything runs on the calling thread
+    pub fn verify_with_threads(&self, is_genesis: bool, threads: usize) -> Result<(), BlockError> {
         // Verify block has transactions
         if self.transactions.is_empty() {
             return Err(BlockError::EmptyTransactions);
         }
 
+        if self
+            .transactions
+            .iter()
+            .skip(1)
+            .any(Transaction::is_coinbase)
+        {
+            return Err(BlockError::MisplacedCoinbase);
+        }
+
         // Verify all transactions are valid
+        self.validate_transactions(is_genesis, threads)?;
+
+        // A sender nonce may be spent only once per block
+        let mut spent = BTreeSet::new();
         for transaction in &self.transactions {
-            transaction.validate(is_genesis)?;
+            if !spent.insert((transaction.sender().as_bytes(), transaction.nonce())) {
+                return Err(BlockError::DuplicateNonce);
+            }
+        }
+
+        if self.header.merkle_root != Self::compute_merkle_root(&self.transactions) {
+            return Err(BlockError::InvalidMerkleRoot);
         }
 
         // Verify hash and proof of work
@@ -120,13 +484,236 @@ impl Block {
         Ok(())
     }
 
+    fn validate_transactions(
+        &self,
+        is_genesis: bool,
+        threads: usize,
+    ) -> Result<(), TransactionError> {
+        // The coinbase is validated like a genesis transaction, as it is
+        // allowed to issue funds
+        let transactions = match self.coinbase() {
+            Some(coinbase) => {
+                coinbase.validate(true)?;
+                &self.transactions[1..]
+            }
+            None => &self.transactions[..],
+        };
+
+        let first_error = |chunk: &[Transaction]| {
+            chunk
+                .iter()
+                .find_map(|transaction| transaction.validate(is_genesis).err())
+        };
+        #[cfg(feature = "std")]
+        if threads > 1 && transactions.len() >= 2 {
+            // Contiguous chunks joined in order, so the first error found is
+            // the one a serial pass would have hit
+            let chunk_size = transactions.len().div_ceil(threads);
+            return std::thread::scope(|scope| {
+                let workers: Vec<_> = transactions
+                    .chunks(chunk_size)
+                    .map(|chunk| scope.spawn(move || first_error(chunk)))
+                    .collect();
+                workers
+                    .into_iter()
+                    .find_map(|worker| worker.join().expect("transaction validation panicked"))
+                    .map_or(Ok(()), Err)
+            });
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = threads;
+
+        first_error(transactions).map_or(Ok(()), Err)
+    }
+
+    /// Expected number of hashes it took to mine the block, `2^difficulty`
+    pub fn work(&self) -> u128 {
+        1u128
+            .checked_shl(self.header.difficulty)
+            .unwrap_or(u128::MAX)
+    }
+
+    /// The block's leading coinbase transaction, if it has one
+    pub fn coinbase(&self) -> Option<&Transaction> {
+        self.transactions.first().filter(|tx| tx.is_coinbase())
+    }
+
+    /// Checks the coinbase, if any, pays out no more than `allowed`
+    pub fn check_coinbase(&self, allowed: u64) -> Result<(), BlockError> {
+        match self.coinbase() {
+            Some(coinbase) if coinbase.amount() > allowed => Err(BlockError::ExcessiveCoinbase),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the block holds at most `max_transactions` transactions
+    pub fn check_size(&self, max_transactions: usize) -> Result<(), BlockError> {
+        if self.transactions.len() > max_transactions {
+            return Err(BlockError::TooManyTransactions);
+        }
+        Ok(())
+    }
+
+    /// Encodes the block in a fixed big-endian layout: timestamp, a `u32`
+    /// transaction count, each transaction (sender, receiver, amount, nonce,
+    /// then a salt flag byte and the salt if present, then the `u32`-length-
+    /// prefixed data payload, then the signature and public key, each as a
+    /// flag byte followed by `u32`-length-prefixed bytes), Merkle root,
+    /// previous hash, hash, nonce and difficulty. Equal blocks always encode
+    /// to equal bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.encoded_len());
+
+        out.extend_from_slice(&encode_timestamp(self.header.timestamp));
+        out.extend_from_slice(&(self.transactions.len() as u32).to_be_bytes());
+        for transaction in &self.transactions {
+            out.extend_from_slice(transaction.sender().as_bytes());
+            out.extend_from_slice(transaction.receiver().as_bytes());
+            out.extend_from_slice(&encode_amount(transaction.amount()));
+            out.extend_from_slice(&encode_u64(transaction.nonce()));
+            match transaction.salt() {
+                Some(salt) => {
+                    out.push(1);
+                    out.extend_from_slice(salt);
+                }
+                None => out.push(0),
+            }
+            out.extend_from_slice(&(transaction.data().len() as u32).to_be_bytes());
+            out.extend_from_slice(transaction.data());
+            put_optional_bytes(&mut out, transaction.signature());
+            put_optional_bytes(&mut out, transaction.public_key());
+        }
+        out.extend_from_slice(&self.header.merkle_root);
+        out.extend_from_slice(&self.header.previous_hash);
+        out.extend_from_slice(&self.hash);
+        out.extend_from_slice(&encode_u64(self.header.nonce));
+        out.extend_from_slice(&self.header.difficulty.to_be_bytes());
+
+        out
+    }
+
+    /// Length of `to_bytes()`, without encoding the block
+    pub fn encoded_len(&self) -> usize {
+        Self::encoded_len_of(&self.transactions)
+    }
+
+    /// Length of `to_bytes()` for a block holding `transactions`
+    pub fn encoded_len_of(transactions: &[Transaction]) -> usize {
+        BLOCK_ENCODED_FIXED
+            + transactions
+                .iter()
+                .map(Self::encoded_transaction_len)
+                .sum::<usize>()
+    }
+
+    /// Bytes `transaction` adds to a block's encoding
+    pub fn encoded_transaction_len(transaction: &Transaction) -> usize {
+        let fixed = if transaction.salt().is_some() {
+            TX_ENCODED_MAX
+        } else {
+            TX_ENCODED_MIN
+        };
+        fixed
+            + transaction.data().len()
+            + optional_bytes_len(transaction.signature())
+            + optional_bytes_len(transaction.public_key())
+    }
+
+    /// Checks the block encodes to at most `max_bytes` bytes
+    pub fn check_encoded_size(&self, max_bytes: usize) -> Result<(), BlockError> {
+        if self.encoded_len() > max_bytes {
+            return Err(BlockError::BlockTooLarge);
+        }
+        Ok(())
+    }
+
+    /// Decodes a block written by `to_bytes`. The stored hash is kept as
+    /// is and the block comes back sealed; call `verify` to check it
+    pub fn from_bytes(data: &[u8]) -> Result<Self, BlockError> {
+        let mut reader = Reader(data);
+
+        let timestamp = OffsetDateTime::from_unix_timestamp(i64::from_be_bytes(reader.take()?))
+            .map_err(|_| BlockError::DecodingError("timestamp out of range"))?;
+
+        let count = u32::from_be_bytes(reader.take()?) as usize;
+        // Every transaction takes at least TX_ENCODED_MIN bytes, so a bogus
+        // count cannot make us reserve more than the input could hold
+        if count > reader.0.len() / TX_ENCODED_MIN {
+            return Err(BlockError::DecodingError("truncated input"));
+        }
+        let mut transactions = Vec::with_capacity(count);
+        for _ in 0..count {
+            let sender = Address::new(reader.take()?);
+            let receiver = Address::new(reader.take()?);
+            let amount = u64::from_be_bytes(reader.take()?);
+            let nonce = u64::from_be_bytes(reader.take()?);
+            let transaction = Transaction::new(sender, receiver, amount, nonce);
+            let transaction = match reader.take::<1>()? {
+                [0] => transaction,
+                [1] => transaction.with_salt(reader.take()?),
+                _ => return Err(BlockError::DecodingError("invalid salt flag")),
+            };
+            let data_len = u32::from_be_bytes(reader.take()?) as usize;
+            let transaction = match reader.take_slice(data_len)? {
+                [] => transaction,
+                data => transaction.with_data(data.to_vec()),
+            };
+            let transaction = match (reader.take_optional()?, reader.take_optional()?) {
+                (None, None) => transaction,
+                (Some(signature), Some(public_key)) => {
+                    transaction.with_signature(signature.to_vec(), public_key.to_vec())
+                }
+                _ => return Err(BlockError::DecodingError("signature without public key")),
+            };
+            transactions.push(transaction);
+        }
+        if transactions.is_empty() {
+            return Err(BlockError::EmptyTransactions);
+        }
+
+        let merkle_root = reader.take()?;
+        let previous_hash = reader.take()?;
+        let hash = reader.take()?;
+        let nonce = u64::from_be_bytes(reader.take()?);
+        let difficulty = u32::from_be_bytes(reader.take()?);
+        if !reader.0.is_empty() {
+            return Err(BlockError::DecodingError("trailing bytes"));
+        }
+
+        Ok(Self {
+            header: BlockHeader {
+                timestamp,
+                previous_hash,
+                merkle_root,
+                nonce,
+                difficulty,
+            },
+            transactions,
+            hash,
+            sealed: true,
+        })
+    }
+
     // Getters
+    pub fn header(&self) -> &BlockHeader {
+        &self.header
+    }
+
     pub fn hash(&self) -> [u8; 32] {
         self.hash
     }
 
+    /// `hash()` as lowercase hex
+    pub fn hash_hex(&self) -> String {
+        to_hex(&self.hash)
+    }
+
+    pub fn merkle_root(&self) -> [u8; 32] {
+        self.header.merkle_root
+    }
+
     pub fn previous_hash(&self) -> [u8; 32] {
-        self.previous_hash
+        self.header.previous_hash
     }
 
     pub fn transactions(&self) -> &[Transaction] {
@@ -134,21 +721,32 @@ impl Block {
     }
 
     pub fn timestamp(&self) -> OffsetDateTime {
-        self.timestamp
+        self.header.timestamp
     }
 
     pub fn nonce(&self) -> u64 {
-        self.nonce
+        self.header.nonce
     }
 
     pub fn difficulty(&self) -> u32 {
-        self.difficulty
+        self.header.difficulty
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.sealed
     }
 
     #[cfg(test)]
     pub fn set_transactions_for_testing(&mut self, transactions: Vec<Transaction>) {
         self.transactions = transactions;
     }
+
+    /// Replaces the timestamp and unseals the block so it can be re-mined
+    #[cfg(test)]
+    pub fn set_timestamp_for_testing(&mut self, timestamp: OffsetDateTime) {
+        self.header.timestamp = timestamp;
+        self.sealed = false;
+    }
 }
 
 #[cfg(test)]
@@ -1,7 +1,7 @@
 use super::*;
 use test_case::test_case;
-use crate::transaction::{Address, Transaction};
-use std::sync::atomic::{AtomicU64, Ordering};
+use crate::transaction::{Address, Transaction, TransactionError};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 static NONCE_COUNTER: AtomicU64 = AtomicU64::new(0);
 
@@ -97,7 +97,7 @@ fn test_proof_validation() {
 
 #[test]
 fn test_nonce_increases_during_mining() {
-    let mut block = create_test_block(4); // Increased difficulty to ensure nonce changes
+    let mut block = create_test_block(16); // Increased difficulty to ensure nonce changes
     let initial_nonce = block.nonce();
     block.mine();
     assert!(block.nonce() > initial_nonce);
@@ -135,7 +135,7 @@ fn test_block_timestamp() {
 
 #[test]
 fn test_mining_resets_hash() {
-    let mut block = create_test_block(4); // Increased difficulty to ensure hash changes
+    let mut block = create_test_block(16); // Increased difficulty to ensure hash changes
     let initial_hash = block.hash();
     block.mine();
     
@@ -146,15 +146,20 @@ fn test_mining_resets_hash() {
 
 #[test]
 fn test_verify_checks_both_hash_and_proof() {
-    let mut block = create_test_block(4); // decent difficulty
+    let mut block = create_test_block(16); // high enough that an unmined block almost never passes
     block.mine();
     assert!(block.verify(false).is_ok(), "Initial valid state failed");
 
-    // Test 1: Invalid hash (modify transaction)
+    // Test 1: Invalid Merkle root (modify transaction)
     let mut invalid_block = block.clone();
     invalid_block.transactions[0] = create_test_transaction(); // modify transaction
-    assert!(matches!(invalid_block.verify(false), Err(BlockError::InvalidHash)), 
-        "Modified transaction should cause invalid hash");
+    assert!(matches!(invalid_block.verify(false), Err(BlockError::InvalidMerkleRoot)),
+        "Modified transaction should cause invalid Merkle root");
+
+    // Test 1b: Invalid hash (root recomputed, so only the hash is stale)
+    invalid_block.header.merkle_root = Block::compute_merkle_root(&invalid_block.transactions);
+    assert!(matches!(invalid_block.verify(false), Err(BlockError::InvalidHash)),
+        "Stale hash should cause invalid hash");
 
     // Test 2: Invalid proof (valid hash but doesn't meet difficulty)
     let invalid_block = Block::new(
@@ -168,4 +173,503 @@ fn test_verify_checks_both_hash_and_proof() {
 
     // Test 3: Verify original block still valid
     assert!(block.verify(false).is_ok(), "Original block should remain valid");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_mining_seals_block() {
+    let mut block = create_test_block(4);
+    assert!(!block.is_sealed());
+
+    block.mine();
+    assert!(block.is_sealed());
+    let sealed = block.clone();
+
+    // Mining is the only public mutation and it is a no-op once sealed
+    block.mine();
+    assert_eq!(block, sealed);
+    assert_eq!(block.transactions(), sealed.transactions());
+    assert!(block.verify(false).is_ok());
+}
+
+#[test]
+fn test_block_hash_covers_transaction_salt() {
+    let mut block = create_test_block(1);
+    block.mine();
+
+    let mut resalted = block.transactions()[0].clone().with_salt([9; 8]);
+    std::mem::swap(&mut block.transactions[0], &mut resalted);
+
+    assert!(matches!(block.verify(false), Err(BlockError::InvalidMerkleRoot)));
+}
+
+#[test]
+fn test_block_hash_covers_transaction_data() {
+    let mut block = create_test_block(1);
+    block.mine();
+
+    block.transactions[0] = block.transactions[0].clone().with_data(b"memo".to_vec());
+
+    assert!(matches!(block.verify(false), Err(BlockError::InvalidMerkleRoot)));
+}
+
+#[test]
+fn test_from_mined_parts_reconstructs_block() {
+    let mut original = create_test_block(8);
+    original.mine();
+
+    let rebuilt = Block::from_mined_parts(
+        original.transactions().to_vec(),
+        original.previous_hash(),
+        original.difficulty(),
+        original.timestamp(),
+        original.nonce(),
+    )
+    .unwrap();
+
+    assert_eq!(rebuilt, original);
+    assert_eq!(rebuilt.hash(), original.hash());
+    assert!(rebuilt.has_valid_proof());
+    assert!(rebuilt.verify(false).is_ok());
+}
+
+#[test]
+fn test_from_mined_parts_with_wrong_nonce_fails_proof() {
+    let mut original = create_test_block(16);
+    original.mine();
+
+    let rebuilt = Block::from_mined_parts(
+        original.transactions().to_vec(),
+        original.previous_hash(),
+        original.difficulty(),
+        original.timestamp(),
+        original.nonce().wrapping_add(1),
+    )
+    .unwrap();
+
+    assert_ne!(rebuilt.hash(), original.hash());
+    assert!(matches!(rebuilt.verify(false), Err(BlockError::InvalidProofOfWork)));
+    assert!(matches!(
+        Block::from_mined_parts(vec![], [0; 32], 1, original.timestamp(), 0),
+        Err(BlockError::EmptyTransactions)
+    ));
+}
+
+#[test]
+fn test_mining_hashes_transactions_once() {
+    let transactions: Vec<_> = (0..50).map(|_| create_test_transaction()).collect();
+    let mut block = Block::new(transactions.clone(), [3; 32], 10).unwrap();
+    let root = block.merkle_root();
+
+    // Swap the transactions behind the header's back: ~1024 attempts at
+    // difficulty 10 that rehashed them would seal a different root
+    block.transactions = vec![create_test_transaction()];
+    block.mine();
+    assert_eq!(block.merkle_root(), root);
+    assert_eq!(block.hash(), block.calculate_hash());
+    assert!(matches!(block.verify(false), Err(BlockError::InvalidMerkleRoot)));
+    block.transactions = transactions.clone();
+
+    // The result matches hashing every field from scratch for the found nonce
+    let leaves: Vec<_> = transactions.iter().map(|tx| tx.hash()).collect();
+    let mut input = Vec::new();
+    input.extend_from_slice(&crate::encoding::encode_timestamp(block.timestamp()));
+    input.extend_from_slice(&[3; 32]);
+    input.extend_from_slice(&crate::merkle::merkle_root(&leaves));
+    input.extend_from_slice(&10u32.to_be_bytes());
+    input.extend_from_slice(&block.nonce().to_be_bytes());
+    let expected = ChainHasher::hash(&input);
+
+    assert_eq!(block.hash(), expected);
+    assert!(block.verify(false).is_ok());
+}
+
+#[test_case(1)]
+#[test_case(2)]
+#[test_case(3)]
+fn test_merkle_root_over_transactions(count: usize) {
+    let transactions: Vec<_> = (0..count).map(|_| create_test_transaction()).collect();
+    let block = Block::new(transactions.clone(), [0; 32], 1).unwrap();
+    let leaves: Vec<_> = transactions.iter().map(|tx| tx.hash()).collect();
+
+    let hash_pair = |left: [u8; 32], right: [u8; 32]| -> [u8; 32] {
+        ChainHasher::hash(&[left, right].concat())
+    };
+    let expected = match leaves[..] {
+        [a] => a,
+        [a, b] => hash_pair(a, b),
+        [a, b, c] => hash_pair(hash_pair(a, b), hash_pair(c, c)),
+        _ => unreachable!(),
+    };
+
+    assert_eq!(block.merkle_root(), expected);
+    assert_eq!(Block::compute_merkle_root(&transactions), expected);
+}
+
+#[test]
+fn test_merkle_proof_for_each_transaction() {
+    use crate::merkle::verify_merkle_proof;
+
+    let transactions: Vec<_> = (0..5).map(|_| create_test_transaction()).collect();
+    let block = Block::new(transactions.clone(), [0; 32], 1).unwrap();
+
+    for (index, tx) in transactions.iter().enumerate() {
+        let mut proof = block.merkle_proof(index).unwrap();
+        assert!(verify_merkle_proof(tx.hash(), &proof, block.merkle_root()));
+
+        proof[0].0[31] ^= 1;
+        assert!(!verify_merkle_proof(tx.hash(), &proof, block.merkle_root()));
+    }
+    assert!(block.merkle_proof(5).is_none());
+}
+
+#[test]
+fn test_verify_rejects_mismatched_merkle_root() {
+    let mut block = create_test_block(1);
+    block.header.merkle_root = [9; 32];
+    block.hash = block.calculate_hash();
+    block.sealed = false;
+    block.mine();
+
+    // Hash and proof of work are fine for the stored root, which is wrong
+    assert_eq!(block.hash(), block.calculate_hash());
+    assert!(matches!(block.verify(false), Err(BlockError::InvalidMerkleRoot)));
+}
+
+#[test]
+fn test_new_block_timestamp_has_whole_seconds() {
+    let block = create_test_block(1);
+    assert_eq!(block.timestamp().nanosecond(), 0);
+}
+
+#[test]
+fn test_bytes_round_trip() {
+    let transactions = vec![
+        create_test_transaction(),
+        create_test_transaction().with_salt([7; 8]),
+        create_test_transaction().with_data(b"memo".to_vec()),
+    ];
+    let mut block = Block::new(transactions, [5; 32], 8).unwrap();
+    block.mine();
+
+    let bytes = block.to_bytes();
+    assert_eq!(bytes, block.to_bytes()); // stable encoding
+    assert_eq!(bytes.len(), 8 + 4 + 63 + 71 + 67 + 32 + 32 + 32 + 8 + 4);
+
+    let decoded = Block::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded, block);
+    assert!(decoded.is_sealed());
+    assert!(decoded.verify(false).is_ok());
+}
+
+#[test]
+fn test_bytes_round_trip_keeps_signature() {
+    let key = k256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+    let mut transaction = create_test_transaction();
+    transaction.sign(&key);
+    let block = Block::new(vec![transaction.clone()], [0; 32], 1).unwrap();
+
+    let decoded = Block::from_bytes(&block.to_bytes()).unwrap();
+    assert_eq!(decoded.transactions()[0], transaction);
+    assert_eq!(decoded.transactions()[0].signature(), transaction.signature());
+    assert_eq!(decoded.hash(), block.hash());
+}
+
+#[test]
+fn test_merkle_root_covers_signature() {
+    let key = k256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+    let mut block = create_test_block(1);
+    block.mine();
+
+    block.transactions[0].sign(&key);
+    assert_ne!(Block::compute_merkle_root(block.transactions()), block.merkle_root());
+    assert!(matches!(block.verify(false), Err(BlockError::TransactionError(_))));
+}
+
+#[test]
+fn test_from_bytes_rejects_malformed_input() {
+    let mut block = create_test_block(1);
+    block.mine();
+    let bytes = block.to_bytes();
+
+    // Every strict prefix is truncated
+    for len in 0..bytes.len() {
+        assert!(
+            matches!(Block::from_bytes(&bytes[..len]), Err(BlockError::DecodingError(_))),
+            "prefix of length {} decoded",
+            len
+        );
+    }
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(matches!(Block::from_bytes(&trailing), Err(BlockError::DecodingError(_))));
+
+    // Salt flag of the only transaction sits right after its 56 fixed bytes
+    let mut bad_flag = bytes.clone();
+    bad_flag[8 + 4 + 56] = 2;
+    assert!(matches!(Block::from_bytes(&bad_flag), Err(BlockError::DecodingError(_))));
+
+    // A huge transaction count is refused without allocating for it
+    let mut bad_count = bytes.clone();
+    bad_count[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert!(matches!(Block::from_bytes(&bad_count), Err(BlockError::DecodingError(_))));
+
+    let mut empty = bytes[..8].to_vec();
+    empty.extend_from_slice(&0u32.to_be_bytes());
+    empty.extend_from_slice(&bytes[8 + 4 + 59..]);
+    assert!(matches!(Block::from_bytes(&empty), Err(BlockError::EmptyTransactions)));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip_of_mined_block() {
+    let mut block = create_test_block(8);
+    block.mine();
+
+    let json = serde_json::to_string(&block).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["hash"], crate::encoding::to_hex(&block.hash()));
+    assert_eq!(value["header"]["previous_hash"], "00".repeat(32));
+    assert_eq!(value["header"]["timestamp"], block.timestamp().unix_timestamp());
+    assert_eq!(value["transactions"][0]["sender"], "01".repeat(20));
+
+    let decoded: Block = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, block);
+    assert!(decoded.verify(false).is_ok());
+}
+
+fn create_signed_transactions(count: u8) -> Vec<Transaction> {
+    (1..=count)
+        .map(|value| {
+            let key = k256::ecdsa::SigningKey::from_slice(&[value; 32]).unwrap();
+            let sender = Address::from_public_key(key.verifying_key());
+            let mut transaction = Transaction::new(sender, create_test_address(2), 100, value as u64);
+            transaction.sign(&key);
+            transaction
+        })
+        .collect()
+}
+
+#[test]
+fn test_parallel_verification_matches_serial() {
+    let mut block = Block::new(create_signed_transactions(40), [0; 32], 1).unwrap();
+    block.mine();
+
+    for threads in [1, 2, 3, 8, 64] {
+        assert!(block.verify_with_threads(false, threads).is_ok(), "{} threads", threads);
+    }
+}
+
+#[test]
+fn test_parallel_verification_rejects_one_bad_signature() {
+    let mut transactions = create_signed_transactions(40);
+    // Two bad transactions; every thread count must report the first
+    let forged = transactions[30].signature().unwrap().to_vec();
+    let key = transactions[30].public_key().unwrap().to_vec();
+    transactions[25] = transactions[25].clone().with_signature(forged, key);
+    transactions[33] = Transaction::new(create_test_address(3), create_test_address(4), 0, 1);
+    let mut block = Block::new(transactions, [0; 32], 1).unwrap();
+    block.mine();
+
+    for threads in [1, 2, 3, 8, 64] {
+        assert!(
+            matches!(
+                block.verify_with_threads(false, threads),
+                Err(BlockError::TransactionError(TransactionError::InvalidSignature))
+            ),
+            "{} threads",
+            threads
+        );
+    }
+}
+
+#[test]
+fn test_header_hash_is_block_hash() {
+    let mut block = create_test_block(8);
+    block.mine();
+    let header = block.header();
+
+    assert_eq!(header.calculate_hash(), block.hash());
+    assert_eq!(header.merkle_root(), Block::compute_merkle_root(block.transactions()));
+    assert_eq!(header.nonce(), block.nonce());
+
+    // The difficulty is part of the header, so relabelling it breaks the hash
+    let mut relabelled = block.clone();
+    relabelled.header.difficulty = 1;
+    assert!(matches!(relabelled.verify(false), Err(BlockError::InvalidHash)));
+}
+
+#[test]
+fn test_mine_with_cancel_stops_immediately() {
+    let mut block = create_test_block(60); // far beyond reach
+    let stop = AtomicBool::new(true);
+
+    assert!(!block.mine_with_cancel(&stop));
+    assert!(!block.is_sealed());
+    assert_eq!(block.nonce(), 0);
+}
+
+#[test]
+fn test_mine_with_cancel_completes_when_not_stopped() {
+    let mut block = create_test_block(8);
+    let stop = AtomicBool::new(false);
+
+    assert!(block.mine_with_cancel(&stop));
+    assert!(block.verify(false).is_ok());
+}
+
+#[test]
+fn test_mine_with_timeout() {
+    let mut block = create_test_block(60);
+    assert!(!block.mine_with_timeout(std::time::Duration::from_millis(20)));
+    assert!(!block.is_sealed());
+
+    let mut block = create_test_block(8);
+    assert!(block.mine_with_timeout(std::time::Duration::from_secs(60)));
+    assert!(block.verify(false).is_ok());
+}
+
+#[test]
+fn test_mine_with_cancel_from_another_thread() {
+    let mut block = create_test_block(60);
+    let stop = AtomicBool::new(false);
+
+    let sealed = std::thread::scope(|scope| {
+        let miner = scope.spawn(|| block.mine_with_cancel(&stop));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        stop.store(true, Ordering::Relaxed);
+        miner.join().unwrap()
+    });
+    assert!(!sealed);
+    assert!(block.nonce() > 0); // it did search before being stopped
+}
+
+#[test_case(1)]
+#[test_case(2)]
+#[test_case(4)]
+fn test_mine_parallel_finds_valid_proof(threads: usize) {
+    let mut block = create_test_block(14);
+    block.mine_parallel(threads);
+
+    assert!(block.is_sealed());
+    assert!(block.has_valid_proof());
+    assert_eq!(block.hash(), block.calculate_hash());
+    assert!(block.verify(false).is_ok());
+
+    // Sealed blocks are left alone
+    let sealed = block.clone();
+    block.mine_parallel(threads);
+    assert_eq!(block, sealed);
+}
+
+#[test]
+fn test_duplicate_sender_nonce_rejected() {
+    let first = create_test_transaction();
+    let double_spend = Transaction::new(
+        first.sender().clone(),
+        create_test_address(3),
+        50,
+        first.nonce(),
+    );
+    let mut block = Block::new(vec![first.clone(), double_spend], [0; 32], 1).unwrap();
+    block.mine();
+    assert!(matches!(block.verify(false), Err(BlockError::DuplicateNonce)));
+
+    // The same nonce from a different sender is fine
+    let other_sender = Transaction::new(create_test_address(3), create_test_address(2), 50, first.nonce());
+    let mut block = Block::new(vec![first, other_sender], [0; 32], 1).unwrap();
+    block.mine();
+    assert!(block.verify(false).is_ok());
+}
+
+#[test]
+fn test_coinbase_must_lead_and_be_unique() {
+    let coinbase = |nonce| Transaction::new(Address::zero(), create_test_address(3), 50, nonce);
+
+    let mut block = Block::new(vec![coinbase(1), create_test_transaction()], [0; 32], 1).unwrap();
+    block.mine();
+    assert!(block.verify(false).is_ok());
+    assert!(block.check_coinbase(50).is_ok());
+    assert!(matches!(block.check_coinbase(49), Err(BlockError::ExcessiveCoinbase)));
+
+    for transactions in [
+        vec![coinbase(1), coinbase(2)],
+        vec![create_test_transaction(), coinbase(1)],
+    ] {
+        let mut block = Block::new(transactions, [0; 32], 1).unwrap();
+        block.mine();
+        assert!(matches!(block.verify(false), Err(BlockError::MisplacedCoinbase)));
+    }
+}
+
+#[test]
+fn test_hash_hex_is_lowercase_hash() {
+    let block = create_test_block(1);
+    let hex = block.hash_hex();
+    assert_eq!(hex, hex.to_lowercase());
+    assert_eq!(crate::encoding::from_hex(&hex), Some(block.hash()));
+}
+
+#[test]
+fn test_check_size() {
+    let block = Block::new(vec![create_test_transaction(), create_test_transaction()], [0; 32], 1).unwrap();
+    assert!(block.check_size(2).is_ok());
+    assert!(matches!(block.check_size(1), Err(BlockError::TooManyTransactions)));
+}
+
+#[test]
+fn test_encoded_len_matches_to_bytes() {
+    let mut signed = create_test_transaction();
+    signed.sign(&k256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap());
+    let transactions = vec![
+        create_test_transaction(),
+        create_test_transaction().with_salt([1; 8]),
+        create_test_transaction().with_data(vec![3; 40]),
+        signed,
+    ];
+    let block = Block::new(transactions, [0; 32], 1).unwrap();
+
+    let len = block.to_bytes().len();
+    assert_eq!(block.encoded_len(), len);
+    assert!(block.check_encoded_size(len).is_ok());
+    assert!(matches!(block.check_encoded_size(len - 1), Err(BlockError::BlockTooLarge)));
+}
+
+#[test]
+fn test_difficulty_target() {
+    assert_eq!(Block::difficulty_target(0), [0xff; 32]);
+
+    let mut expected = [0xff; 32];
+    expected[0] = 0x00;
+    expected[1] = 0x0f;
+    assert_eq!(Block::difficulty_target(12), expected);
+    assert_eq!(Block::difficulty_target(256), [0; 32]);
+    assert_eq!(Block::difficulty_target(u32::MAX), [0; 32]);
+
+    for difficulty in 0..256 {
+        assert!(Block::difficulty_target(difficulty + 1) < Block::difficulty_target(difficulty));
+    }
+}
+
+#[test]
+fn test_proof_compares_hash_to_target() {
+    let mut block = create_test_block(8);
+    block.mine();
+    let target = Block::difficulty_target(block.difficulty());
+    assert!(block.hash() <= target);
+    assert!(block.has_valid_proof());
+    assert_eq!(block.hash()[0], 0);
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+fn test_block_hashed_with_sha256_is_rejected() {
+    let mut block = Block::new(vec![create_test_transaction()], [0; 32], 1).unwrap();
+    block.mine();
+
+    let mut input = block.header.hash_prefix();
+    input[76..].copy_from_slice(&block.nonce().to_be_bytes());
+    block.hash = crate::hash::Sha256Hasher::hash(&input);
+
+    assert!(matches!(block.verify(false), Err(BlockError::InvalidHash)));
+}
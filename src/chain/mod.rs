@@ -1,7 +1,41 @@
 use crate::block::{Block, BlockError};
 use crate::mempool::{Mempool, MempoolError};
-use crate::transaction::{Address, Transaction};
+use crate::store::{BlockStore, MemoryBlockStore};
+use crate::transaction::{Address, Transaction, ValidationRule};
+use crate::utxo::UtxoSet;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::slice;
+use std::sync::Arc;
 use thiserror::Error;
+use time::{Duration, OffsetDateTime};
+
+/// How many preceding blocks feed the median-time-past check
+const MEDIAN_TIME_SPAN: usize = 11;
+
+/// How far past local time a block may be timestamped, by default
+const MAX_FUTURE_DRIFT: Duration = Duration::hours(2);
+
+/// Default for `ChainConfig::max_txs_per_block`
+const DEFAULT_MAX_TXS_PER_BLOCK: usize = 10;
+
+/// Maps a block's difficulty to the most transactions it may hold
+pub type BlockSizePolicy = fn(difficulty: u32) -> usize;
+
+/// Told about changes to a chain as they happen, e.g. for logging or
+/// metrics. Every method does nothing by default
+pub trait ChainObserver: Send + Sync {
+    /// A block was appended at the tip
+    fn on_block_added(&self, _block: &Block) {}
+
+    /// A transaction was accepted into the mempool
+    fn on_transaction_submitted(&self, _transaction: &Transaction) {}
+
+    /// The chain switched to another branch and is now `new_len` blocks
+    /// long. Blocks connected this way are not also reported as added
+    fn on_reorg(&self, _new_len: usize) {}
+}
 
 #[derive(Error, Debug)]
 pub enum ChainError {
@@ -15,62 +49,792 @@ pub enum ChainError {
     EmptyChain,
     #[error("Mempool error: {0}")]
     MempoolError(#[from] MempoolError),
+    #[error("Block timestamp is below the median of recent blocks")]
+    TimestampBelowMedian,
+    #[error("Block does not follow its parent by more than the minimum interval")]
+    BlockTooSoon,
+    #[error("Block timestamp is before its parent's")]
+    TimestampBeforeParent,
+    #[error("Block timestamp is too far in the future")]
+    TimestampInFuture,
+    #[error("Branch does not carry more work than the current tip")]
+    BranchNotLonger,
+    #[error("Chain does not match checkpoint at height {height}")]
+    CheckpointMismatch { height: u64 },
+    #[error("Block at height {height} repeats an earlier block")]
+    DuplicateBlock { height: u64 },
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Block at height {height} does not have the retargeted difficulty")]
+    UnexpectedDifficulty { height: u64 },
+    #[error("Sender does not have enough funds for the transaction")]
+    InsufficientFunds,
+    #[error("Block skips or reuses a sender nonce")]
+    NonceOutOfOrder,
+    #[error("Block at height {height} is invalid: {source}")]
+    BlockInvalid {
+        height: u64,
+        source: Box<ChainError>,
+    },
+}
+
+/// Timestamps (Unix seconds) of the most recent blocks, for median-time-past
+#[derive(Default)]
+struct TimestampWindow(VecDeque<i64>);
+
+impl TimestampWindow {
+    fn push(&mut self, block: &Block) {
+        if self.0.len() == MEDIAN_TIME_SPAN {
+            self.0.pop_front();
+        }
+        self.0.push_back(block.timestamp().unix_timestamp());
+    }
+
+    fn median(&self) -> Option<i64> {
+        let mut sorted: Vec<i64> = self.0.iter().copied().collect();
+        sorted.sort_unstable();
+        sorted.get(sorted.len() / 2).copied()
+    }
+
+    /// A block must be timestamped after the median of the window
+    fn check(&self, block: &Block) -> Result<(), ChainError> {
+        match self.median() {
+            Some(median) if block.timestamp().unix_timestamp() <= median => {
+                Err(ChainError::TimestampBelowMedian)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The current time, or the earliest second after the median when
+    /// blocks come faster than one a second
+    fn next_timestamp(&self) -> OffsetDateTime {
+        let now = OffsetDateTime::now_utc();
+        self.median()
+            .and_then(|median| OffsetDateTime::from_unix_timestamp(median + 1).ok())
+            .map_or(now, |earliest| now.max(earliest))
+    }
+}
+
+/// The highest nonce each sender has spent. A sender's transactions must
+/// use consecutive nonces, starting from 0
+#[derive(Debug, Default, Clone)]
+struct AccountNonces(HashMap<[u8; 20], u64>);
+
+impl AccountNonces {
+    /// The nonce `sender`'s next transaction must carry
+    fn next(&self, sender: &Address) -> u64 {
+        self.0
+            .get(sender.as_bytes())
+            .map_or(0, |nonce| nonce.saturating_add(1))
+    }
+
+    fn push(&mut self, block: &Block) {
+        for transaction in block.transactions().iter().filter(|tx| !tx.is_coinbase()) {
+            let highest = self
+                .0
+                .entry(*transaction.sender().as_bytes())
+                .or_insert(transaction.nonce());
+            *highest = (*highest).max(transaction.nonce());
+        }
+    }
+
+    /// Each sender's transactions in `block` must carry its next nonces in
+    /// order, with no gaps and no repeats
+    fn check(&self, block: &Block) -> Result<(), ChainError> {
+        let mut expected = HashMap::new();
+        for transaction in block.transactions().iter().filter(|tx| !tx.is_coinbase()) {
+            let next = expected
+                .entry(transaction.sender().as_bytes())
+                .or_insert_with(|| self.next(transaction.sender()));
+            if transaction.nonce() != *next {
+                return Err(ChainError::NonceOutOfOrder);
+            }
+            *next += 1;
+        }
+        Ok(())
+    }
+}
+
+/// What each address holds, the same totals as the UTXO set without its
+/// outputs. A transaction may only spend what its sender holds once the
+/// transactions before it in the block are applied
+#[derive(Debug, Default, Clone)]
+struct AccountBalances(HashMap<Address, u64>);
+
+impl AccountBalances {
+    fn get(&self, address: &Address) -> u64 {
+        self.0.get(address).copied().unwrap_or(0)
+    }
+
+    /// Credits each receiver and debits each sender but coinbases, which
+    /// mint their amount. A sender short of the amount, which `check` only
+    /// lets through in genesis, ends at zero, as in the UTXO set
+    fn push(&mut self, block: &Block) {
+        for transaction in block.transactions() {
+            if !transaction.is_coinbase() {
+                let sent = self.0.entry(transaction.sender().clone()).or_default();
+                *sent = sent.saturating_sub(transaction.amount());
+            }
+            let received = self.0.entry(transaction.receiver().clone()).or_default();
+            *received = received.saturating_add(transaction.amount());
+        }
+    }
+
+    /// Each non-coinbase transaction in `block` must be covered by its
+    /// sender's balance after the transactions before it
+    fn check(&self, block: &Block) -> Result<(), ChainError> {
+        let mut balances = HashMap::new();
+        for transaction in block.transactions() {
+            if !transaction.is_coinbase() {
+                let sent = balances
+                    .entry(transaction.sender())
+                    .or_insert_with(|| self.get(transaction.sender()));
+                *sent = sent
+                    .checked_sub(transaction.amount())
+                    .ok_or(ChainError::InsufficientFunds)?;
+            }
+            let received = balances
+                .entry(transaction.receiver())
+                .or_insert_with(|| self.get(transaction.receiver()));
+            *received = received.saturating_add(transaction.amount());
+        }
+        Ok(())
+    }
+}
+
+/// How the genesis block of a new chain is built
+#[derive(Debug, Clone)]
+pub struct GenesisConfig {
+    pub transaction: Option<Transaction>, // Defaults to a 1-unit transfer from the zero address
+    pub genesis_difficulty: u32,          // Usually lower than the operating difficulty
+}
+
+/// How a chain steers its difficulty toward a target block time
+#[derive(Debug, Clone, Copy)]
+pub struct RetargetConfig {
+    pub target_block_time: Duration, // Desired gap between consecutive blocks
+    pub retarget_interval: usize,    // Blocks between adjustments; values below 2 count as 2
+}
+
+/// Everything `Chain::from_config` needs to start a chain. Start from
+/// `default()` and override what matters, so new settings can be added
+/// without breaking callers
+#[derive(Debug, Clone)]
+pub struct ChainConfig {
+    pub difficulty: u32, // Difficulty of the first block after genesis
+    pub genesis_transaction: Option<Transaction>, // See `GenesisConfig::transaction`
+    pub genesis_difficulty: Option<u32>, // Defaults to `difficulty`
+    pub retarget: Option<RetargetConfig>, // Keeps `difficulty` fixed when unset
+    pub block_reward: u64, // Zero disables coinbases
+    pub halving_interval: Option<u64>, // The reward never halves when unset
+    pub mempool_capacity: Option<usize>, // Unbounded when unset
+    pub max_txs_per_block: usize, // Including any coinbase
+    pub max_block_bytes: Option<usize>, // Limit on `Block::encoded_len`; unbounded when unset
+    pub max_data_len: Option<usize>, // Per-transaction data limit below `MAX_DATA_LEN`, if any
+    pub genesis_timestamp: Option<OffsetDateTime>, // Current time when unset
+    pub genesis_hash: Option<[u8; 32]>, // Hash the genesis block must have, if pinned
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            difficulty: 1,
+            genesis_transaction: None,
+            genesis_difficulty: None,
+            retarget: None,
+            block_reward: 0,
+            halving_interval: None,
+            mempool_capacity: None,
+            max_txs_per_block: DEFAULT_MAX_TXS_PER_BLOCK,
+            max_block_bytes: None,
+            max_data_len: None,
+            genesis_timestamp: None,
+            genesis_hash: None,
+        }
+    }
+}
+
+impl ChainConfig {
+    pub fn with_difficulty(mut self, difficulty: u32) -> Self {
+        self.difficulty = difficulty;
+        self
+    }
+
+    pub fn with_genesis_transaction(mut self, transaction: Transaction) -> Self {
+        self.genesis_transaction = Some(transaction);
+        self
+    }
+
+    pub fn with_genesis_difficulty(mut self, difficulty: u32) -> Self {
+        self.genesis_difficulty = Some(difficulty);
+        self
+    }
+
+    pub fn with_retarget(mut self, retarget: RetargetConfig) -> Self {
+        self.retarget = Some(retarget);
+        self
+    }
+
+    pub fn with_block_reward(mut self, reward: u64) -> Self {
+        self.block_reward = reward;
+        self
+    }
+
+    pub fn with_halving_interval(mut self, interval: u64) -> Self {
+        self.halving_interval = Some(interval);
+        self
+    }
+
+    pub fn with_mempool_capacity(mut self, capacity: usize) -> Self {
+        self.mempool_capacity = Some(capacity);
+        self
+    }
+
+    pub fn with_max_txs_per_block(mut self, max: usize) -> Self {
+        self.max_txs_per_block = max;
+        self
+    }
+
+    pub fn with_max_block_bytes(mut self, max: usize) -> Self {
+        self.max_block_bytes = Some(max);
+        self
+    }
+
+    /// Caps each transaction's data payload at `max` bytes. Payloads over
+    /// `MAX_DATA_LEN` are invalid whatever this is set to
+    pub fn with_max_data_len(mut self, max: usize) -> Self {
+        self.max_data_len = Some(max);
+        self
+    }
+
+    /// Stamps the genesis block `timestamp`. Nodes that must agree on the
+    /// genesis hash need this, as the hash covers the timestamp
+    pub fn with_genesis_timestamp(mut self, timestamp: OffsetDateTime) -> Self {
+        self.genesis_timestamp = Some(timestamp);
+        self
+    }
+
+    /// Makes `Chain::from_config` fail unless the genesis block hashes to
+    /// `hash`
+    pub fn with_genesis_hash(mut self, hash: [u8; 32]) -> Self {
+        self.genesis_hash = Some(hash);
+        self
+    }
+
+    /// The genesis block these settings describe
+    fn genesis(&self) -> GenesisConfig {
+        GenesisConfig {
+            transaction: self.genesis_transaction.clone(),
+            genesis_difficulty: self.genesis_difficulty.unwrap_or(self.difficulty),
+        }
+    }
+}
+
+/// How the main chain changed in a reorganization
+#[derive(Debug, Clone)]
+pub struct ReorgResult {
+    pub disconnected: Vec<Block>, // Former main-chain blocks above the fork, lowest first
+    pub connected: Vec<Block>,    // New main-chain blocks above the fork, lowest first
+    pub fork_height: u64,         // Height of the last block both chains share
+}
+
+/// What `Chain::reorg_to` would do with a branch, worked out without applying it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainEvaluation {
+    pub would_replace: bool, // The branch is valid and has more work than what it replaces
+    pub fork_height: u64,    // Height of the last block both chains share
+    pub reorg_depth: u64,    // Main-chain blocks that would be disconnected
+}
+
+/// A vertex in the exported transaction graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphNode {
+    Address(Address),
+    Transaction([u8; 32]),
+}
+
+/// A directed edge of value flow: sender to transaction, or transaction to receiver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphEdge {
+    pub from: GraphNode,
+    pub to: GraphNode,
+    pub amount: u64,
+}
+
+/// A block with the context explorers show next to it
+#[derive(Debug, Clone)]
+pub struct BlockInfo<'a> {
+    pub block: &'a Block,
+    pub confirmations: u64, // 1 for the tip, growing as blocks are built on it
+    pub cumulative_work: u128, // Work of this block and every block below it
+    pub size: usize,        // Length of the block's `to_bytes` encoding
+}
+
+/// A single problem found by `Chain::verify_report`
+#[derive(Debug)]
+pub struct VerifyFailure {
+    pub height: usize,
+    pub tx_index: Option<usize>, // Set when the failure is in one transaction
+    pub error: ChainError,
+}
+
+/// Every problem found in a chain, in height order
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    failures: Vec<VerifyFailure>,
+}
+
+impl VerifyReport {
+    fn push(&mut self, height: usize, tx_index: Option<usize>, error: impl Into<ChainError>) {
+        self.failures.push(VerifyFailure {
+            height,
+            tx_index,
+            error: error.into(),
+        });
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn failures(&self) -> &[VerifyFailure] {
+        &self.failures
+    }
+}
+
+/// What the main chain's blocks add up to, kept current as blocks are
+/// appended and rebuilt when the chain is replaced
+#[derive(Debug, Default, Clone)]
+struct ChainState {
+    utxos: UtxoSet,
+    tx_locations: HashMap<[u8; 32], (usize, usize)>, // Height and position of each transaction
+    nonces: AccountNonces,                           // Highest nonce each sender has spent
+    balances: AccountBalances,                       // What each address holds
+}
+
+impl ChainState {
+    fn apply(&mut self, block: &Block, height: usize) {
+        for (tx_index, transaction) in block.transactions().iter().enumerate() {
+            self.utxos
+                .apply_transaction(transaction, height as u64, tx_index as u32);
+            self.tx_locations
+                .insert(transaction.hash(), (height, tx_index));
+        }
+        self.nonces.push(block);
+        self.balances.push(block);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.utxos.shrink_to_fit();
+        self.tx_locations.shrink_to_fit();
+        self.nonces.0.shrink_to_fit();
+        self.balances.0.shrink_to_fit();
+    }
 }
 
-pub struct Chain {
-    blocks: Vec<Block>,
+/// Mines the genesis block `genesis` describes, stamped `timestamp`
+fn mine_genesis(genesis: &GenesisConfig, timestamp: OffsetDateTime) -> Result<Block, ChainError> {
+    let genesis_tx = genesis.transaction.clone().unwrap_or_else(|| {
+        Transaction::new(
+            Address::zero(), // Genesis sender
+            Address::zero(), // Same address for genesis
+            1,               // Genesis amount
+            0,               // Genesis nonce
+        )
+    });
+
+    let mut genesis_block = Block::new_at(
+        vec![genesis_tx],
+        [0; 32],
+        genesis.genesis_difficulty,
+        timestamp,
+    )?;
+    genesis_block.mine();
+    Ok(genesis_block)
+}
+
+fn total_work<'a>(blocks: impl Iterator<Item = &'a Block>) -> u128 {
+    blocks.fold(0, |work, block| work.saturating_add(block.work()))
+}
+
+/// Splits `len` bytes off the front of a chain file being read
+fn take_bytes<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], ChainError> {
+    if data.len() < len {
+        return Err(BlockError::DecodingError("truncated chain file").into());
+    }
+    let (head, rest) = data.split_at(len);
+    *data = rest;
+    Ok(head)
+}
+
+fn take_u32(data: &mut &[u8]) -> Result<u32, ChainError> {
+    take_bytes(data, 4).map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+pub struct Chain<S: BlockStore = MemoryBlockStore> {
+    blocks: S,
     current_difficulty: u32,
+    genesis_difficulty: u32,
     mempool: Mempool,
+    rules: Vec<Arc<dyn ValidationRule>>,
+    size_policy: Option<BlockSizePolicy>,
+    max_txs_per_block: usize,
+    max_block_bytes: Option<usize>,
+    max_data_len: Option<usize>,
+    min_block_interval: Duration,
+    max_future_drift: Duration,
+    verification_threads: usize,
+    initial_difficulty: u32, // Difficulty of block 1 when retargeting
+    retarget: Option<RetargetConfig>,
+    state: ChainState,
+    block_reward: u64, // Coinbase allowance for block 1, before any halving
+    halving_interval: Option<u64>,
+    miner_address: Option<Address>, // Where `add_block` pays the reward
+    observer: Option<Box<dyn ChainObserver>>,
 }
 
 impl Chain {
     pub fn new(difficulty: u32, genesis_tx: Option<Transaction>) -> Result<Self, ChainError> {
-        let genesis_tx = genesis_tx.unwrap_or_else(|| {
-            Transaction::new(
-                Address::new([0; 20]), // Genesis sender
-                Address::new([0; 20]), // Same address for genesis
-                1,                     // Genesis amount
-                0,                     // Genesis nonce
-            )
-        });
+        Self::from_config(ChainConfig {
+            genesis_transaction: genesis_tx,
+            ..ChainConfig::default().with_difficulty(difficulty)
+        })
+    }
+
+    /// Creates an in-memory chain set up as `config` describes
+    pub fn from_config(config: ChainConfig) -> Result<Self, ChainError> {
+        let mut store = MemoryBlockStore::new();
+        if let Some(timestamp) = config.genesis_timestamp {
+            store.put(mine_genesis(&config.genesis(), timestamp)?);
+        }
+        Self::with_config(store, config)
+    }
+
+    /// Creates a chain over `store` with `config`'s settings, mining a
+    /// genesis block if the store is empty
+    fn with_config(store: MemoryBlockStore, config: ChainConfig) -> Result<Self, ChainError> {
+        let mut chain = Self::with_store(store, config.difficulty, config.genesis())?;
+        if config
+            .genesis_hash
+            .is_some_and(|hash| hash != chain.genesis_hash())
+        {
+            return Err(ChainError::InvalidGenesis);
+        }
+        chain.retarget = config.retarget;
+        chain.block_reward = config.block_reward;
+        chain.halving_interval = config.halving_interval;
+        chain.max_txs_per_block = config.max_txs_per_block;
+        chain.max_block_bytes = config.max_block_bytes;
+        chain.max_data_len = config.max_data_len;
+        if let Some(capacity) = config.mempool_capacity {
+            chain.mempool = Mempool::with_capacity(capacity);
+        }
+        chain.retarget_tip();
+        Ok(chain)
+    }
+
+    /// Creates a chain whose difficulty starts at `difficulty` and, given a
+    /// `retarget`, is then steered toward its target block time. Blocks may
+    /// pay their miner `block_reward`, halved every `halving_interval`
+    /// blocks
+    pub fn new_with_config(
+        difficulty: u32,
+        genesis_tx: Option<Transaction>,
+        retarget: Option<RetargetConfig>,
+        block_reward: u64,
+        halving_interval: Option<u64>,
+    ) -> Result<Self, ChainError> {
+        Self::from_config(ChainConfig {
+            difficulty,
+            genesis_transaction: genesis_tx,
+            retarget,
+            block_reward,
+            halving_interval,
+            ..ChainConfig::default()
+        })
+    }
+
+    /// Creates a chain that starts from an existing mempool, e.g. one
+    /// pre-populated with known transactions
+    pub fn new_with_mempool(
+        difficulty: u32,
+        genesis_tx: Option<Transaction>,
+        mempool: Mempool,
+    ) -> Result<Self, ChainError> {
+        let mut chain = Self::new(difficulty, genesis_tx)?;
+        chain.mempool = mempool;
+        Ok(chain)
+    }
 
-        let mut genesis_block = Block::new(vec![genesis_tx], [0; 32], difficulty)?;
+    /// Creates a chain whose genesis block is mined at its own difficulty
+    pub fn new_with_genesis(difficulty: u32, genesis: GenesisConfig) -> Result<Self, ChainError> {
+        Self::with_store(MemoryBlockStore::new(), difficulty, genesis)
+    }
+
+    /// Builds a chain from raw blocks (e.g. received from a peer) under the
+    /// consensus rules in `config`, rebuilding the hash index and running
+    /// `verify`. The blocks supply the genesis block, so `config`'s genesis
+    /// transaction and timestamp are unused
+    pub fn try_from_blocks(blocks: Vec<Block>, config: ChainConfig) -> Result<Self, ChainError> {
+        if blocks.is_empty() {
+            return Err(ChainError::EmptyChain);
+        }
 
-        genesis_block.mine();
+        let mut store = MemoryBlockStore::new();
+        for block in blocks {
+            store.put(block);
+        }
+
+        let chain = Self::with_config(store, config)?;
+        chain.verify()?;
+        Ok(chain)
+    }
 
-        Ok(Self {
-            blocks: vec![genesis_block],
+    /// Reads a chain written by `save_to_file` and verifies it under
+    /// `config`, which must carry the consensus settings the chain was built
+    /// with; the file holds only blocks and difficulties. The saved current
+    /// difficulty is restored, and the saved genesis difficulty applies
+    /// unless `config` sets one. The mempool starts empty and rules, size
+    /// policy and block interval are unset
+    pub fn load_from_file(path: &Path, mut config: ChainConfig) -> Result<Self, ChainError> {
+        let data = fs::read(path)?;
+        let mut rest = data.as_slice();
+
+        let difficulty = take_u32(&mut rest)?;
+        let genesis_difficulty = take_u32(&mut rest)?;
+        let count = take_u32(&mut rest)?;
+        let mut blocks = Vec::new();
+        for _ in 0..count {
+            let len = take_u32(&mut rest)? as usize;
+            blocks.push(Block::from_bytes(take_bytes(&mut rest, len)?)?);
+        }
+        if !rest.is_empty() {
+            return Err(BlockError::DecodingError("trailing bytes in chain file").into());
+        }
+
+        config.genesis_difficulty.get_or_insert(genesis_difficulty);
+        let mut chain = Self::try_from_blocks(blocks, config)?;
+        chain.current_difficulty = difficulty;
+        Ok(chain)
+    }
+}
+
+impl<S: BlockStore> Chain<S> {
+    /// Creates a chain backed by `store`. An empty store gets a freshly mined
+    /// genesis block; a non-empty one is used as-is (call `verify` to check it)
+    pub fn with_store(
+        mut store: S,
+        difficulty: u32,
+        genesis: GenesisConfig,
+    ) -> Result<Self, ChainError> {
+        if store.is_empty() {
+            store.put(mine_genesis(&genesis, OffsetDateTime::now_utc())?);
+        }
+
+        let mut chain = Self {
+            blocks: store,
             current_difficulty: difficulty,
+            genesis_difficulty: genesis.genesis_difficulty,
             mempool: Mempool::new(),
-        })
+            rules: Vec::new(),
+            size_policy: None,
+            max_txs_per_block: DEFAULT_MAX_TXS_PER_BLOCK,
+            max_block_bytes: None,
+            max_data_len: None,
+            min_block_interval: Duration::ZERO,
+            max_future_drift: MAX_FUTURE_DRIFT,
+            verification_threads: 1,
+            initial_difficulty: difficulty,
+            retarget: None,
+            state: ChainState::default(),
+            block_reward: 0,
+            halving_interval: None,
+            miner_address: None,
+            observer: None,
+        };
+        chain.rebuild_state();
+        Ok(chain)
     }
 
+    /// Adds a rule applied to submitted transactions and to transactions
+    /// passed to `add_block_with_transactions`
+    pub fn add_validation_rule(&mut self, rule: Arc<dyn ValidationRule>) {
+        self.mempool.add_rule(rule.clone());
+        self.rules.push(rule);
+    }
+
+    /// Makes the allowed transaction count per block a function of its
+    /// difficulty. This is a consensus rule: `verify` rejects non-genesis
+    /// blocks over the limit with `BlockError::TooManyTransactions`
+    pub fn set_block_size_policy(&mut self, policy: BlockSizePolicy) {
+        self.size_policy = Some(policy);
+    }
+
+    /// Rejects blocks timestamped no more than `interval` after their
+    /// parent. Zero, the default, disables the check
+    pub fn set_min_block_interval(&mut self, interval: Duration) {
+        self.min_block_interval = interval;
+    }
+
+    /// Rejects blocks timestamped more than `drift` past local time. The
+    /// default is two hours
+    pub fn set_max_future_drift(&mut self, drift: Duration) {
+        self.max_future_drift = drift;
+    }
+
+    /// Caps the threads used to validate a block's transactions and their
+    /// signatures. One, the default, validates serially
+    pub fn set_verification_threads(&mut self, threads: usize) {
+        self.verification_threads = threads.max(1);
+    }
+
+    /// Pays the block reward to `address` in blocks built by `add_block`.
+    /// Without a miner address blocks are built without a coinbase
+    pub fn set_miner_address(&mut self, address: Address) {
+        self.miner_address = Some(address);
+    }
+
+    /// Reports blocks, transactions and reorgs to `observer` from now on,
+    /// replacing any earlier observer
+    pub fn set_observer(&mut self, observer: Box<dyn ChainObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Checks `transaction` against the main chain's state: the sender's
+    /// unspent outputs, less what its pending transactions already pay out,
+    /// must cover the amount
+    pub fn validate_transaction(&self, transaction: &Transaction) -> Result<(), ChainError> {
+        let pending = self.mempool.pending_outflow(transaction.sender());
+        let available = self
+            .balance_of(transaction.sender())
+            .saturating_sub(pending);
+        if transaction.amount() > available {
+            return Err(ChainError::InsufficientFunds);
+        }
+        Ok(())
+    }
+
+    /// Validates `transaction` and queues it in the mempool. A transaction
+    /// that would not fit in a block of `max_block_bytes` on its own is
+    /// refused, as it could never be mined
     pub fn submit_transaction(&mut self, transaction: Transaction) -> Result<(), ChainError> {
-        self.mempool.add_transaction(transaction)?;
+        self.check_submission(&transaction)?;
+        match &self.observer {
+            Some(observer) => {
+                self.mempool.add_transaction(transaction.clone())?;
+                observer.on_transaction_submitted(&transaction);
+            }
+            None => self.mempool.add_transaction(transaction)?,
+        }
+        Ok(())
+    }
+
+    /// The checks a transaction must pass before it is queued in the mempool
+    fn check_submission(&self, transaction: &Transaction) -> Result<(), ChainError> {
+        self.validate_transaction(transaction)?;
+        if self
+            .max_block_bytes
+            .is_some_and(|max| Block::encoded_len_of(slice::from_ref(transaction)) > max)
+        {
+            return Err(BlockError::BlockTooLarge.into());
+        }
+        if let Some(max) = self.max_data_len {
+            transaction
+                .check_data_len(max)
+                .map_err(MempoolError::from)?;
+        }
         Ok(())
     }
 
     pub fn add_block(&mut self) -> Result<(), ChainError> {
         let previous_block = self.blocks.last().ok_or(ChainError::EmptyChain)?;
 
-        let transactions = self.mempool.get_transactions(10);
+        let coinbase = self.coinbase_for(self.blocks.len() as u64);
+
+        // The coinbase takes one of the block's slots
+        let limit = self
+            .max_transactions(self.current_difficulty)
+            .saturating_sub(coinbase.is_some() as usize);
+
+        // Only a sender's next nonces in sequence can go in, and only while
+        // its balance covers them; a transaction after a gap or one its
+        // sender cannot pay for waits in the mempool
+        let mut expected = HashMap::new();
+        let mut balances = HashMap::new();
+        if let Some(coinbase) = &coinbase {
+            let miner = coinbase.receiver();
+            let reward = self
+                .state
+                .balances
+                .get(miner)
+                .saturating_add(coinbase.amount());
+            balances.insert(miner.clone(), reward);
+        }
+        let mut transactions: Vec<Transaction> = self
+            .mempool
+            .get_transactions(self.mempool.len())
+            .into_iter()
+            .filter(|tx| {
+                let next = expected
+                    .entry(*tx.sender().as_bytes())
+                    .or_insert_with(|| self.state.nonces.next(tx.sender()));
+                if tx.nonce() != *next {
+                    return false;
+                }
+                let sent = balances
+                    .entry(tx.sender().clone())
+                    .or_insert_with(|| self.state.balances.get(tx.sender()));
+                let Some(left) = sent.checked_sub(tx.amount()) else {
+                    return false;
+                };
+                *sent = left;
+                *next += 1;
+                let received = balances
+                    .entry(tx.receiver().clone())
+                    .or_insert_with(|| self.state.balances.get(tx.receiver()));
+                *received = received.saturating_add(tx.amount());
+                true
+            })
+            .take(limit)
+            .collect();
+
+        // Take transactions in order until the next one would not fit
+        if let Some(max_bytes) = self.max_block_bytes {
+            let mut size = Block::encoded_len_of(coinbase.as_slice());
+            let fitting = transactions
+                .iter()
+                .take_while(|tx| {
+                    size += Block::encoded_transaction_len(tx);
+                    size <= max_bytes
+                })
+                .count();
+            // Returning Ok here would leave the head of the queue blocking
+            // every later block without the caller noticing
+            if fitting == 0 && !transactions.is_empty() {
+                return Err(BlockError::BlockTooLarge.into());
+            }
+            transactions.truncate(fitting);
+        }
 
         if transactions.is_empty() {
             // todo: handle empty mempool
             return Ok(());
         }
 
-        let mut new_block = Block::new(
-            transactions.clone(),
+        let block_transactions = coinbase.into_iter().chain(transactions.clone()).collect();
+        let mut new_block = Block::new_at(
+            block_transactions,
             previous_block.hash(),
             self.current_difficulty,
+            self.recent_timestamps().next_timestamp(),
         )?;
 
         new_block.mine();
-        new_block.verify(false)?;
+        self.check_next_block(&new_block)?;
 
         self.mempool.remove_transactions(&transactions);
-        self.blocks.push(new_block);
+        self.append(new_block);
 
         Ok(())
     }
@@ -81,43 +845,648 @@ impl Chain {
     ) -> Result<(), ChainError> {
         let previous_block = self.blocks.last().ok_or(ChainError::EmptyChain)?;
 
-        let mut new_block =
-            Block::new(transactions, previous_block.hash(), self.current_difficulty)?;
+        // A coinbase may issue funds; `check_next_block` checks its position
+        // and holds it to the block reward
+        for transaction in &transactions {
+            transaction
+                .validate_with_rules(transaction.is_coinbase(), &self.rules)
+                .map_err(BlockError::from)?;
+        }
+
+        let mut new_block = Block::new_at(
+            transactions,
+            previous_block.hash(),
+            self.current_difficulty,
+            self.recent_timestamps().next_timestamp(),
+        )?;
 
         new_block.mine();
-        new_block.verify(false)?;
+        self.check_next_block(&new_block)?;
 
-        self.blocks.push(new_block);
+        self.append(new_block);
         Ok(())
     }
 
+    /// Checks `branch`, a run of blocks whose first block builds on an
+    /// existing block, and reports whether `reorg_to` would switch to it:
+    /// the branch must be fully valid and carry more work than the blocks
+    /// above the fork it would replace. Nothing is changed
+    pub fn evaluate_chain(&self, branch: &[Block]) -> Result<ChainEvaluation, ChainError> {
+        let first = branch.first().ok_or(ChainError::BranchNotLonger)?;
+        let fork_height = self
+            .height_of(&first.previous_hash())
+            .ok_or(ChainError::InvalidBlockLink)?;
+
+        let mut window = self.timestamps_before(fork_height + 1);
+        let mut nonces = self.nonces_through(fork_height);
+        let mut balances = self.balances_through(fork_height);
+        let mut parent = self
+            .blocks
+            .get(fork_height)
+            .ok_or(ChainError::InvalidBlockLink)?;
+        let block_at = |height: usize| match height.checked_sub(fork_height + 1) {
+            Some(offset) => branch.get(offset),
+            None => self.blocks.get(height),
+        };
+        for (height, block) in (fork_height + 1..).zip(branch) {
+            if block.previous_hash() != parent.hash() {
+                return Err(ChainError::InvalidBlockLink);
+            }
+            block.verify_with_threads(false, self.verification_threads)?;
+            self.check_difficulty(height, block, block_at)?;
+            block.check_coinbase(self.block_reward(height as u64))?;
+            self.check_block_size(block)?;
+            nonces.check(block)?;
+            balances.check(block)?;
+            self.check_interval(parent, block)?;
+            window.check(block)?;
+            self.check_timestamp(parent, block)?;
+            window.push(block);
+            nonces.push(block);
+            balances.push(block);
+            parent = block;
+        }
+
+        let replaced_work = total_work(self.blocks.iter().skip(fork_height + 1));
+        let branch_work = total_work(branch.iter());
+
+        Ok(ChainEvaluation {
+            would_replace: branch_work > replaced_work,
+            fork_height: fork_height as u64,
+            reorg_depth: (self.blocks.len() - fork_height - 1) as u64,
+        })
+    }
+
+    /// Switches the main chain to `branch` if `evaluate_chain` accepts it.
+    /// Transactions from disconnected blocks that the branch neither
+    /// includes nor replaces with another of the same nonce go back to the
+    /// mempool if they would still be accepted by `submit_transaction`
+    pub fn reorg_to(&mut self, branch: Vec<Block>) -> Result<ReorgResult, ChainError> {
+        let evaluation = self.evaluate_chain(&branch)?;
+        if !evaluation.would_replace {
+            return Err(ChainError::BranchNotLonger);
+        }
+        Ok(self.switch_to(evaluation.fork_height as usize, branch))
+    }
+
+    /// Replaces the main chain with `candidate`, a whole chain starting at
+    /// our genesis block, if it is longer and valid end to end. Unlike
+    /// `reorg_to`, which follows the most work, this follows the longest
+    /// chain. A candidate that is no longer gives `Ok(false)`; one that is
+    /// invalid or starts from another genesis gives an error. Either way the
+    /// chain is left untouched
+    pub fn try_replace(&mut self, mut candidate: Vec<Block>) -> Result<bool, ChainError> {
+        if candidate.len() <= self.blocks.len() {
+            return Ok(false);
+        }
+        if candidate.first().map(Block::hash) != self.blocks.get(0).map(Block::hash) {
+            return Err(ChainError::InvalidGenesis);
+        }
+
+        // Blocks both chains already share need no checking
+        let shared = candidate
+            .iter()
+            .zip(self.blocks.iter())
+            .take_while(|(theirs, ours)| theirs.hash() == ours.hash())
+            .count();
+        let branch = candidate.split_off(shared);
+        let evaluation = self.evaluate_chain(&branch)?;
+
+        self.switch_to(evaluation.fork_height as usize, branch);
+        Ok(true)
+    }
+
+    /// Swaps the blocks above `fork_height` for an already checked `branch`
+    fn switch_to(&mut self, fork_height: usize, branch: Vec<Block>) -> ReorgResult {
+        let disconnected: Vec<Block> = self.blocks.iter().skip(fork_height + 1).cloned().collect();
+        self.blocks.truncate(fork_height + 1);
+        for block in &branch {
+            self.mempool.remove_transactions(block.transactions());
+            self.blocks.put(block.clone());
+        }
+        self.rebuild_state();
+        self.retarget_tip();
+        self.drop_stale_transactions();
+
+        let confirmed: HashSet<[u8; 32]> = branch
+            .iter()
+            .flat_map(|block| block.transactions())
+            .map(|tx| tx.hash())
+            .collect();
+        for tx in disconnected.iter().flat_map(|block| block.transactions()) {
+            // Best effort: a transaction whose nonce the branch has used, that
+            // the branch cannot fund, or that our policy refuses is dropped
+            if !confirmed.contains(&tx.hash())
+                && tx.nonce() >= self.state.nonces.next(tx.sender())
+                && self.check_submission(tx).is_ok()
+            {
+                self.mempool.add_transaction(tx.clone()).ok();
+            }
+        }
+
+        if let Some(observer) = &self.observer {
+            observer.on_reorg(self.blocks.len());
+        }
+
+        ReorgResult {
+            disconnected,
+            connected: branch,
+            fork_height: fork_height as u64,
+        }
+    }
+
+    /// Checks every block from genesis up, stopping at the first failure. A
+    /// failing block is reported as `BlockInvalid` with its height and
+    /// cause; `verify_report` lists every failure instead
     pub fn verify(&self) -> Result<(), ChainError> {
+        self.verify_from(0)
+    }
+
+    /// Checks the blocks from `height` up like `verify`, trusting those
+    /// below it: their stored hashes, timestamps and nonces are read but not
+    /// rechecked, so `verify_from(len - 1)` only rehashes the tip. Passes
+    /// trivially when `height` is past the tip
+    pub fn verify_from(&self, height: usize) -> Result<(), ChainError> {
         // chain should never be empty
         if self.blocks.is_empty() {
             return Err(ChainError::EmptyChain);
         }
 
-        // verify genesis block
-        let genesis_block = &self.blocks[0];
-        if genesis_block.previous_hash() != [0; 32] {
+        if height == 0 {
+            let genesis_block = self.blocks.get(0).ok_or(ChainError::EmptyChain)?;
+            self.check_genesis(genesis_block)
+                .map_err(|source| ChainError::BlockInvalid {
+                    height: 0,
+                    source: Box::new(source),
+                })?;
+        }
+
+        // verify rest of the chain against the trusted blocks below `start`
+        let start = height.max(1);
+        let Some(mut previous_block) = self.blocks.get(start - 1) else {
+            return Ok(());
+        };
+        let mut window = self.timestamps_before(start);
+        let mut nonces = self.nonces_through(start - 1);
+        let mut balances = self.balances_through(start - 1);
+        let mut seen: HashSet<[u8; 32]> = self.blocks.iter().take(start).map(Block::hash).collect();
+        for (height, current_block) in (start as u64..).zip(self.blocks.iter().skip(start)) {
+            // Checked before the link so a repeated block is reported as such
+            if !seen.insert(current_block.hash()) {
+                return Err(ChainError::DuplicateBlock { height });
+            }
+            self.check_chained_block(
+                height,
+                previous_block,
+                current_block,
+                &window,
+                &nonces,
+                &balances,
+            )
+            .map_err(|source| ChainError::BlockInvalid {
+                height,
+                source: Box::new(source),
+            })?;
+            window.push(current_block);
+            nonces.push(current_block);
+            balances.push(current_block);
+            previous_block = current_block;
+        }
+        Ok(())
+    }
+
+    /// Runs `verify`'s checks on the genesis block
+    fn check_genesis(&self, genesis_block: &Block) -> Result<(), ChainError> {
+        if genesis_block.previous_hash() != [0; 32]
+            || genesis_block.difficulty() != self.genesis_difficulty
+        {
             return Err(ChainError::InvalidGenesis);
         }
-        genesis_block.verify(true)?;
+        genesis_block.verify_with_threads(true, self.verification_threads)?;
+        Ok(())
+    }
 
-        // verify rest of the chain
-        for window in self.blocks.windows(2) {
-            let previous_block = &window[0];
-            let current_block = &window[1];
+    /// Runs `verify`'s checks on the block at `height`, given its parent and
+    /// the timestamps, nonces and balances the blocks below it leave
+    fn check_chained_block(
+        &self,
+        height: u64,
+        parent: &Block,
+        block: &Block,
+        window: &TimestampWindow,
+        nonces: &AccountNonces,
+        balances: &AccountBalances,
+    ) -> Result<(), ChainError> {
+        if block.previous_hash() != parent.hash() {
+            return Err(ChainError::InvalidBlockLink);
+        }
 
-            if current_block.previous_hash() != previous_block.hash() {
-                return Err(ChainError::InvalidBlockLink);
+        block.verify_with_threads(false, self.verification_threads)?;
+        self.check_difficulty(height as usize, block, |h| self.blocks.get(h))?;
+        block.check_coinbase(self.block_reward(height))?;
+        self.check_block_size(block)?;
+        nonces.check(block)?;
+        balances.check(block)?;
+        self.check_interval(parent, block)?;
+        window.check(block)?;
+        self.check_timestamp(parent, block)?;
+        Ok(())
+    }
+
+    /// Writes the current and genesis difficulty followed by every block,
+    /// each prefixed with its encoded length. The mempool is not saved
+    pub fn save_to_file(&self, path: &Path) -> Result<(), ChainError> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.current_difficulty.to_be_bytes());
+        out.extend_from_slice(&self.genesis_difficulty.to_be_bytes());
+        out.extend_from_slice(&(self.blocks.len() as u32).to_be_bytes());
+        for block in self.blocks.iter() {
+            let bytes = block.to_bytes();
+            out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            out.extend_from_slice(&bytes);
+        }
+
+        fs::write(path, out)?;
+        Ok(())
+    }
+
+    /// Checks the chain against trusted `(height, hash)` checkpoints, failing
+    /// at the lowest height whose block is missing or has a different hash
+    pub fn verify_checkpoints(&self, checkpoints: &[(u64, [u8; 32])]) -> Result<(), ChainError> {
+        let mut sorted = checkpoints.to_vec();
+        sorted.sort_unstable_by_key(|(height, _)| *height);
+
+        for (height, expected_hash) in sorted {
+            let block = usize::try_from(height)
+                .ok()
+                .and_then(|index| self.blocks.get(index));
+            if block.map(|b| b.hash()) != Some(expected_hash) {
+                return Err(ChainError::CheckpointMismatch { height });
             }
+        }
+        Ok(())
+    }
 
-            current_block.verify(false)?;
+    /// Runs the checks a block must pass before it is appended at the tip
+    fn check_next_block(&self, block: &Block) -> Result<(), ChainError> {
+        let parent = self.blocks.last().ok_or(ChainError::EmptyChain)?;
+        block.verify_with_threads(false, self.verification_threads)?;
+        self.check_difficulty(self.blocks.len(), block, |h| self.blocks.get(h))?;
+        block.check_coinbase(self.block_reward(self.blocks.len() as u64))?;
+        self.check_block_size(block)?;
+        self.state.nonces.check(block)?;
+        self.state.balances.check(block)?;
+        self.check_interval(parent, block)?;
+        self.recent_timestamps().check(block)?;
+        self.check_timestamp(parent, block)?;
+        Ok(())
+    }
+
+    /// A block may not be timestamped before its parent, nor more than
+    /// `max_future_drift` past local time
+    fn check_timestamp(&self, parent: &Block, block: &Block) -> Result<(), ChainError> {
+        if block.timestamp() < parent.timestamp() {
+            return Err(ChainError::TimestampBeforeParent);
+        }
+        if block.timestamp() > OffsetDateTime::now_utc() + self.max_future_drift {
+            return Err(ChainError::TimestampInFuture);
         }
         Ok(())
     }
 
+    /// Compared in whole seconds, the resolution the block hash commits to
+    fn check_interval(&self, parent: &Block, block: &Block) -> Result<(), ChainError> {
+        if self.min_block_interval.is_zero() {
+            return Ok(());
+        }
+
+        let elapsed = block.timestamp().unix_timestamp() - parent.timestamp().unix_timestamp();
+        if elapsed <= self.min_block_interval.whole_seconds() {
+            return Err(ChainError::BlockTooSoon);
+        }
+        Ok(())
+    }
+
+    /// With retargeting on, checks `block` has the difficulty the algorithm
+    /// gives for `height`, looking up lower blocks through `block_at`
+    fn check_difficulty<'a>(
+        &self,
+        height: usize,
+        block: &Block,
+        block_at: impl Fn(usize) -> Option<&'a Block>,
+    ) -> Result<(), ChainError> {
+        match self.expected_difficulty(height, block_at) {
+            Some(expected) if block.difficulty() != expected => {
+                Err(ChainError::UnexpectedDifficulty {
+                    height: height as u64,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The difficulty a block at `height` must have, or `None` when
+    /// retargeting is off. Block 1 uses the initial difficulty and later
+    /// blocks inherit their parent's, except every `retarget_interval`
+    /// blocks: if the last interval's blocks came more than twice as fast
+    /// as the target, difficulty rises by one bit, and if more than twice
+    /// as slow, it drops by one (to no less than 1)
+    fn expected_difficulty<'a>(
+        &self,
+        height: usize,
+        block_at: impl Fn(usize) -> Option<&'a Block>,
+    ) -> Option<u32> {
+        let config = self.retarget?;
+        if height <= 1 {
+            return Some(self.initial_difficulty);
+        }
+
+        let parent = block_at(height - 1)?;
+        let interval = config.retarget_interval.max(2);
+        if !height.is_multiple_of(interval) {
+            return Some(parent.difficulty());
+        }
+
+        // Timestamps are whole seconds, as the block hash commits to
+        let first = block_at(height - interval)?;
+        let actual = parent.timestamp().unix_timestamp() - first.timestamp().unix_timestamp();
+        let expected = config.target_block_time.whole_seconds() * (interval as i64 - 1);
+        Some(if actual.saturating_mul(2) < expected {
+            parent.difficulty().saturating_add(1)
+        } else if actual > expected.saturating_mul(2) {
+            parent.difficulty().saturating_sub(1).max(1)
+        } else {
+            parent.difficulty()
+        })
+    }
+
+    /// The most a coinbase at `height` may pay: the block reward halved once
+    /// per elapsed `halving_interval`. Transactions carry no fees, so there
+    /// is nothing to add on top. Genesis issuance is not limited by this
+    pub fn block_reward(&self, height: u64) -> u64 {
+        let halvings = match self.halving_interval {
+            Some(interval) if interval > 0 => height / interval,
+            _ => 0,
+        };
+        u32::try_from(halvings)
+            .ok()
+            .and_then(|halvings| self.block_reward.checked_shr(halvings))
+            .unwrap_or(0)
+    }
+
+    /// The coinbase `add_block` puts first in a block at `height`, if there
+    /// is a miner to pay and a reward to pay them. The height is used as the
+    /// nonce so every coinbase hashes differently
+    fn coinbase_for(&self, height: u64) -> Option<Transaction> {
+        let miner = self.miner_address.clone()?;
+        match self.block_reward(height) {
+            0 => None,
+            reward => Some(Transaction::new(Address::zero(), miner, reward, height)),
+        }
+    }
+
+    /// Stores an already checked block at the tip and applies its effects
+    fn append(&mut self, block: Block) {
+        self.state.apply(&block, self.blocks.len());
+        if let Some(observer) = &self.observer {
+            observer.on_block_added(&block);
+        }
+        self.blocks.put(block);
+        self.retarget_tip();
+        self.drop_stale_transactions();
+    }
+
+    /// Removes pending transactions whose nonce the main chain has already
+    /// used, since no block could include them
+    fn drop_stale_transactions(&mut self) {
+        let nonces = &self.state.nonces;
+        self.mempool
+            .retain(|tx| tx.nonce() >= nonces.next(tx.sender()));
+    }
+
+    /// Replays the whole main chain into fresh balances and indexes
+    fn rebuild_state(&mut self) {
+        self.state = ChainState::default();
+        for (height, block) in self.blocks.iter().enumerate() {
+            self.state.apply(block, height);
+        }
+    }
+
+    /// Points `current_difficulty` at what the next block must use
+    fn retarget_tip(&mut self) {
+        if let Some(next) = self.expected_difficulty(self.blocks.len(), |h| self.blocks.get(h)) {
+            self.current_difficulty = next;
+        }
+    }
+
+    /// How many transactions a block at `difficulty` may hold: the
+    /// chain-wide limit, lowered by any size policy
+    fn max_transactions(&self, difficulty: u32) -> usize {
+        match self.size_policy {
+            Some(policy) => policy(difficulty).min(self.max_txs_per_block),
+            None => self.max_txs_per_block,
+        }
+    }
+
+    /// Checks the transaction count, byte and data limits
+    fn check_block_size(&self, block: &Block) -> Result<(), BlockError> {
+        block.check_size(self.max_transactions(block.difficulty()))?;
+        if let Some(max_bytes) = self.max_block_bytes {
+            block.check_encoded_size(max_bytes)?;
+        }
+        if let Some(max) = self.max_data_len {
+            for transaction in block.transactions() {
+                transaction.check_data_len(max)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The timestamp window a block appended at the tip is checked against
+    fn recent_timestamps(&self) -> TimestampWindow {
+        self.timestamps_before(self.blocks.len())
+    }
+
+    /// The timestamp window for a block at `height`
+    fn timestamps_before(&self, height: usize) -> TimestampWindow {
+        let mut window = TimestampWindow::default();
+        let start = height.saturating_sub(MEDIAN_TIME_SPAN);
+        for block in self.blocks.iter().skip(start).take(height - start) {
+            window.push(block);
+        }
+        window
+    }
+
+    /// Sender nonces after the main-chain blocks up to and including `height`
+    fn nonces_through(&self, height: usize) -> AccountNonces {
+        let mut nonces = AccountNonces::default();
+        for block in self.blocks.iter().take(height + 1) {
+            nonces.push(block);
+        }
+        nonces
+    }
+
+    /// Balances after the main-chain blocks up to and including `height`
+    fn balances_through(&self, height: usize) -> AccountBalances {
+        let mut balances = AccountBalances::default();
+        for block in self.blocks.iter().take(height + 1) {
+            balances.push(block);
+        }
+        balances
+    }
+
+    /// Checks the whole chain like `verify`, but keeps going after a failure
+    /// and reports every invalid block and transaction it finds
+    pub fn verify_report(&self) -> VerifyReport {
+        let mut report = VerifyReport::default();
+
+        if self.blocks.is_empty() {
+            report.push(0, None, ChainError::EmptyChain);
+            return report;
+        }
+
+        let mut previous: Option<&Block> = None;
+        let mut window = TimestampWindow::default();
+        let mut nonces = AccountNonces::default();
+        let mut balances = AccountBalances::default();
+        let mut seen = HashSet::new();
+        for (height, block) in self.blocks.iter().enumerate() {
+            let is_genesis = height == 0;
+
+            if !seen.insert(block.hash()) {
+                let error = ChainError::DuplicateBlock {
+                    height: height as u64,
+                };
+                report.push(height, None, error);
+            }
+
+            match previous {
+                None if block.previous_hash() != [0; 32]
+                    || block.difficulty() != self.genesis_difficulty =>
+                {
+                    report.push(height, None, ChainError::InvalidGenesis);
+                }
+                Some(parent) if block.previous_hash() != parent.hash() => {
+                    report.push(height, None, ChainError::InvalidBlockLink);
+                }
+                _ => {}
+            }
+            if let Some(Err(e)) = previous.map(|parent| self.check_interval(parent, block)) {
+                report.push(height, None, e);
+            }
+            if let Some(Err(e)) = previous.map(|parent| self.check_timestamp(parent, block)) {
+                report.push(height, None, e);
+            }
+            previous = Some(block);
+
+            if block.transactions().is_empty() {
+                report.push(height, None, BlockError::EmptyTransactions);
+            }
+
+            for (tx_index, transaction) in block.transactions().iter().enumerate() {
+                if tx_index > 0 && transaction.is_coinbase() {
+                    report.push(height, Some(tx_index), BlockError::MisplacedCoinbase);
+                    continue;
+                }
+                let issues_funds = is_genesis || (tx_index == 0 && transaction.is_coinbase());
+                if let Err(e) = transaction.validate(issues_funds) {
+                    report.push(height, Some(tx_index), BlockError::from(e));
+                }
+            }
+
+            if block.merkle_root() != Block::compute_merkle_root(block.transactions()) {
+                report.push(height, None, BlockError::InvalidMerkleRoot);
+            }
+
+            if block.hash() != block.calculate_hash() {
+                report.push(height, None, BlockError::InvalidHash);
+            }
+
+            if !block.has_valid_proof() {
+                report.push(height, None, BlockError::InvalidProofOfWork);
+            }
+
+            if !is_genesis {
+                if let Err(e) = self.check_block_size(block) {
+                    report.push(height, None, e);
+                }
+                if let Err(e) = self.check_difficulty(height, block, |h| self.blocks.get(h)) {
+                    report.push(height, None, e);
+                }
+                if let Err(e) = block.check_coinbase(self.block_reward(height as u64)) {
+                    report.push(height, None, e);
+                }
+                if let Err(e) = nonces.check(block) {
+                    report.push(height, None, e);
+                }
+                if let Err(e) = balances.check(block) {
+                    report.push(height, None, e);
+                }
+            }
+            nonces.push(block);
+            balances.push(block);
+
+            if let Err(e) = window.check(block) {
+                report.push(height, None, e);
+            }
+            window.push(block);
+        }
+
+        report
+    }
+
+    /// Total value transferred across the chain. The genesis block and
+    /// coinbases only issue funds, so they are not counted
+    pub fn total_volume(&self) -> u128 {
+        self.volume_in_range(0, self.blocks.len())
+    }
+
+    /// Value transferred in blocks at heights `from..to`, excluding genesis
+    /// and coinbase issuance
+    pub fn volume_in_range(&self, from: usize, to: usize) -> u128 {
+        self.blocks
+            .iter()
+            .enumerate()
+            .skip(from.max(1))
+            .take(to.saturating_sub(from.max(1)))
+            .flat_map(|(_, block)| block.transactions())
+            .filter(|tx| !tx.is_coinbase())
+            .map(|tx| tx.amount() as u128)
+            .sum()
+    }
+
+    /// Exports every confirmed transaction as two edges, sender -> tx and
+    /// tx -> receiver, in chain order
+    pub fn export_graph(&self) -> Vec<GraphEdge> {
+        self.blocks
+            .iter()
+            .flat_map(|block| block.transactions())
+            .flat_map(|tx| {
+                let node = GraphNode::Transaction(tx.hash());
+                [
+                    GraphEdge {
+                        from: GraphNode::Address(tx.sender().clone()),
+                        to: node.clone(),
+                        amount: tx.amount(),
+                    },
+                    GraphEdge {
+                        from: node,
+                        to: GraphNode::Address(tx.receiver().clone()),
+                        amount: tx.amount(),
+                    },
+                ]
+            })
+            .collect()
+    }
+
+    /// Shrinks the block store, derived state and mempool to fit their
+    /// contents, for long-running nodes recovering from a memory spike
+    pub fn compact(&mut self) {
+        self.blocks.compact();
+        self.state.shrink_to_fit();
+        self.mempool.shrink_to_fit();
+    }
+
     pub fn len(&self) -> usize {
         self.blocks.len()
     }
@@ -130,13 +1499,209 @@ impl Chain {
         self.blocks.get(index)
     }
 
+    pub fn get_block_by_hash(&self, hash: &[u8; 32]) -> Option<&Block> {
+        self.blocks.get_by_hash(hash)
+    }
+
+    /// Height of the main-chain block with `hash`
+    pub fn height_of(&self, hash: &[u8; 32]) -> Option<usize> {
+        self.blocks.height_of(hash)
+    }
+
+    /// The main chain's blocks from genesis to the tip
+    pub fn iter(&self) -> impl Iterator<Item = &Block> {
+        self.blocks.iter()
+    }
+
     pub fn current_difficulty(&self) -> u32 {
         self.current_difficulty
     }
 
+    pub fn genesis_difficulty(&self) -> u32 {
+        self.genesis_difficulty
+    }
+
+    /// Pins the difficulty used for blocks added from now on. A chain that
+    /// retargets overrides it after the next block
+    pub fn set_difficulty(&mut self, difficulty: u32) {
+        self.current_difficulty = difficulty;
+    }
+
+    pub fn latest_block(&self) -> Option<&Block> {
+        self.blocks.last()
+    }
+
+    /// Hash of the genesis block, which every chain holds
+    pub fn genesis_hash(&self) -> [u8; 32] {
+        self.blocks
+            .get(0)
+            .expect("a chain always holds its genesis block")
+            .hash()
+    }
+
+    /// Height and hash of the block `confirmations` below the tip, a
+    /// heuristic "final" point unlikely to be reorganized away
+    pub fn safe_tip(&self, confirmations: u64) -> Option<(u64, [u8; 32])> {
+        let tip_height = self.blocks.len().checked_sub(1)? as u64;
+        let height = tip_height.checked_sub(confirmations)?;
+        let block = self.blocks.get(usize::try_from(height).ok()?)?;
+        Some((height, block.hash()))
+    }
+
+    /// Bundles the block at `height` with its confirmations, cumulative
+    /// work and encoded size
+    pub fn block_info(&self, height: u64) -> Option<BlockInfo<'_>> {
+        let index = usize::try_from(height).ok()?;
+        let block = self.blocks.get(index)?;
+        let tip_height = self.blocks.len() as u64 - 1;
+        let cumulative_work = total_work(self.blocks.iter().take(index + 1));
+
+        Some(BlockInfo {
+            block,
+            confirmations: tip_height - height + 1,
+            cumulative_work,
+            size: block.to_bytes().len(),
+        })
+    }
+
+    /// Value `address` holds on the main chain, the total of its unspent
+    /// outputs in `utxos`
+    pub fn balance_of(&self, address: &Address) -> u64 {
+        self.state.balances.get(address)
+    }
+
+    /// The nonce `address` should use next: one past the highest it has
+    /// spent on the main chain or has pending in the mempool, or 0 for an
+    /// address that has never sent
+    pub fn next_nonce(&self, address: &Address) -> u64 {
+        let pending = self.mempool.highest_nonce(address);
+        let after_pending = pending.map_or(0, |nonce| nonce.saturating_add(1));
+        self.state.nonces.next(address).max(after_pending)
+    }
+
+    pub fn utxos(&self) -> &UtxoSet {
+        &self.state.utxos
+    }
+
+    /// Every transaction waiting in the mempool, in the order `add_block`
+    /// considers them
+    pub fn pending_transactions(&self) -> Vec<Transaction> {
+        self.mempool.get_transactions(self.mempool.len())
+    }
+
+    /// Height of the main-chain block holding the transaction with
+    /// `tx_hash`, and the transaction itself
+    pub fn find_transaction(&self, tx_hash: &[u8; 32]) -> Option<(usize, &Transaction)> {
+        let &(height, tx_index) = self.state.tx_locations.get(tx_hash)?;
+        let transaction = self.blocks.get(height)?.transactions().get(tx_index)?;
+        Some((height, transaction))
+    }
+
+    pub fn store(&self) -> &S {
+        &self.blocks
+    }
+
+    /// Copies the main chain's blocks, difficulty and UTXO set into a
+    /// read-only view that later changes to the chain do not affect. The
+    /// mempool is left out
+    pub fn snapshot(&self) -> ChainSnapshot {
+        let mut blocks = MemoryBlockStore::new();
+        for block in self.blocks.iter() {
+            blocks.put(block.clone());
+        }
+
+        ChainSnapshot {
+            blocks,
+            current_difficulty: self.current_difficulty,
+            genesis_difficulty: self.genesis_difficulty,
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<'a, S: BlockStore> IntoIterator for &'a Chain<S> {
+    type Item = &'a Block;
+    type IntoIter = Box<dyn Iterator<Item = &'a Block> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.blocks.iter()
+    }
+}
+
+/// A frozen copy of a chain taken by `Chain::snapshot`, safe to hand to
+/// another thread while the chain keeps growing
+#[derive(Debug, Clone)]
+pub struct ChainSnapshot {
+    blocks: MemoryBlockStore,
+    current_difficulty: u32,
+    genesis_difficulty: u32,
+    state: ChainState,
+}
+
+impl ChainSnapshot {
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn get_block(&self, index: usize) -> Option<&Block> {
+        self.blocks.get(index)
+    }
+
+    pub fn get_block_by_hash(&self, hash: &[u8; 32]) -> Option<&Block> {
+        self.blocks.get_by_hash(hash)
+    }
+
+    /// Height of the block with `hash`
+    pub fn height_of(&self, hash: &[u8; 32]) -> Option<usize> {
+        self.blocks.height_of(hash)
+    }
+
+    /// The blocks from genesis to the tip at the time of the snapshot
+    pub fn iter(&self) -> impl Iterator<Item = &Block> {
+        self.blocks.iter()
+    }
+
     pub fn latest_block(&self) -> Option<&Block> {
         self.blocks.last()
     }
+
+    pub fn current_difficulty(&self) -> u32 {
+        self.current_difficulty
+    }
+
+    pub fn genesis_difficulty(&self) -> u32 {
+        self.genesis_difficulty
+    }
+
+    /// Value `address` held, the total of its unspent outputs in `utxos`
+    pub fn balance_of(&self, address: &Address) -> u64 {
+        self.state.balances.get(address)
+    }
+
+    pub fn utxos(&self) -> &UtxoSet {
+        &self.state.utxos
+    }
+
+    /// Height of the block holding the transaction with `tx_hash`, and the
+    /// transaction itself
+    pub fn find_transaction(&self, tx_hash: &[u8; 32]) -> Option<(usize, &Transaction)> {
+        let &(height, tx_index) = self.state.tx_locations.get(tx_hash)?;
+        let transaction = self.blocks.get(height)?.transactions().get(tx_index)?;
+        Some((height, transaction))
+    }
+}
+
+impl<'a> IntoIterator for &'a ChainSnapshot {
+    type Item = &'a Block;
+    type IntoIter = Box<dyn Iterator<Item = &'a Block> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.blocks.iter()
+    }
 }
 
 #[cfg(test)]
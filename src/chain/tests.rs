@@ -1,15 +1,32 @@
 use super::*;
-use crate::transaction::{Transaction, Address};
-use std::sync::atomic::{AtomicU64, Ordering};
+use crate::store::{BlockStore, MemoryBlockStore};
+use crate::transaction::{Transaction, Address, TransactionError, ValidationRule};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use time::macros::datetime;
 
-static NONCE_COUNTER: AtomicU64 = AtomicU64::new(0);
+thread_local! {
+    // Each test runs on its own thread, so its sender nonces start at 0
+    // and follow on without gaps, as blocks require
+    static NEXT_NONCE: Cell<u64> = const { Cell::new(0) };
+}
+
+fn next_nonce() -> u64 {
+    NEXT_NONCE.with(|nonce| nonce.replace(nonce.get() + 1))
+}
+
+// Rewinds `create_test_transaction` to the nonce `chain` expects next, after
+// building transactions the chain refused
+fn resync_nonces(chain: &Chain) {
+    NEXT_NONCE.with(|nonce| nonce.set(chain.next_nonce(&create_test_address(1))));
+}
 
 fn create_test_address(value: u8) -> Address {
     Address::new([value; 20])
 }
 
 fn create_test_transaction() -> Transaction {
-    let nonce = NONCE_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let nonce = next_nonce();
     Transaction::new(
         create_test_address(1),  // sender
         create_test_address(2),  // receiver
@@ -18,9 +35,15 @@ fn create_test_transaction() -> Transaction {
     )
 }
 
+// Genesis payment that funds the sender of `create_test_transaction`
+fn funding_genesis() -> Transaction {
+    Transaction::new(Address::zero(), create_test_address(1), 1_000_000_000, 0)
+}
+
+// Without an explicit genesis transaction the chain starts with `funding_genesis`
 fn create_test_chain(difficulty: Option<u32>, genesis_tx: Option<Transaction>) -> Result<Chain, ChainError> {
     let difficulty = difficulty.unwrap_or(1);
-    Chain::new(difficulty, genesis_tx)
+    Chain::new(difficulty, genesis_tx.or_else(|| Some(funding_genesis())))
 }
 
 #[test]
@@ -107,7 +130,7 @@ fn test_chain_verification() {
 #[test]
 fn test_empty_chain_verification() {
     let mut chain = create_test_chain(None, None).unwrap();
-    chain.blocks.clear(); // Force empty chain for testing
+    chain.blocks.clear_for_testing(); // Force empty chain for testing
     
     assert!(matches!(chain.verify(), Err(ChainError::EmptyChain)));
 }
@@ -115,7 +138,7 @@ fn test_empty_chain_verification() {
 #[test]
 fn test_block_difficulty_matches_chain() {
     let difficulty = 2;
-    let mut chain = Chain::new(difficulty, None).unwrap();
+    let mut chain = Chain::new(difficulty, Some(funding_genesis())).unwrap();
     
     chain.submit_transaction(create_test_transaction()).unwrap();
     chain.add_block().unwrap();
@@ -133,13 +156,97 @@ fn test_chain_tamper_detection() {
     chain.add_block().unwrap();
     
     // Tamper with the last block
-    if let Some(block) = chain.blocks.last_mut() {
+    let tip = chain.len() - 1;
+    if let Some(block) = chain.blocks.get_mut_for_testing(tip) {
         block.set_transactions_for_testing(vec![create_test_transaction()]);
     }
 
     assert!(chain.verify().is_err());
 }
 
+#[test]
+fn test_verify_names_first_failing_height() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 4);
+    assert_eq!(chain.len(), 5);
+
+    let payment = Transaction::new(create_test_address(1), create_test_address(3), 7, 0);
+    chain.blocks.get_mut_for_testing(3).unwrap().set_transactions_for_testing(vec![payment]);
+
+    let error = chain.verify().unwrap_err();
+    assert!(matches!(
+        &error,
+        ChainError::BlockInvalid { height: 3, source }
+            if matches!(**source, ChainError::BlockValidation(BlockError::InvalidMerkleRoot))
+    ));
+    assert!(error.to_string().starts_with("Block at height 3 is invalid: "));
+}
+
+#[test]
+fn test_verify_from_checks_only_later_blocks() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 4);
+    let tip = chain.len() - 1;
+    assert!(chain.verify_from(tip).is_ok());
+    assert!(chain.verify_from(chain.len()).is_ok());
+
+    // Blocks below the starting height are trusted, not rehashed
+    let payment = Transaction::new(create_test_address(1), create_test_address(3), 7, 0);
+    chain.blocks.get_mut_for_testing(1).unwrap().set_transactions_for_testing(vec![payment.clone()]);
+    assert!(chain.verify_from(tip).is_ok());
+    assert!(matches!(chain.verify_from(1), Err(ChainError::BlockInvalid { height: 1, .. })));
+    assert!(matches!(chain.verify(), Err(ChainError::BlockInvalid { height: 1, .. })));
+
+    chain.blocks.get_mut_for_testing(tip).unwrap().set_transactions_for_testing(vec![payment]);
+    assert!(matches!(
+        chain.verify_from(tip),
+        Err(ChainError::BlockInvalid { height, source })
+            if height == tip as u64 && matches!(*source, ChainError::BlockValidation(BlockError::InvalidMerkleRoot))
+    ));
+}
+
+#[test]
+fn test_snapshot_is_unaffected_by_later_blocks() {
+    let mut chain = create_test_chain(None, Some(funding_genesis())).unwrap();
+    mine_blocks(&mut chain, 2);
+    let snapshot = chain.snapshot();
+    let tip = chain.latest_block().unwrap().clone();
+    let balance = chain.balance_of(&create_test_address(1));
+
+    mine_blocks(&mut chain, 2);
+    chain.set_difficulty(3);
+    assert_eq!(chain.len(), 5);
+
+    let reporter = std::thread::spawn(move || snapshot);
+    let snapshot = reporter.join().unwrap();
+    assert_eq!(snapshot.len(), 3);
+    assert_eq!(snapshot.latest_block(), Some(&tip));
+    assert_eq!(snapshot.height_of(&tip.hash()), Some(2));
+    assert_eq!(snapshot.current_difficulty(), 1);
+    assert_eq!(snapshot.balance_of(&create_test_address(1)), balance);
+    assert_ne!(chain.balance_of(&create_test_address(1)), balance);
+    assert!(snapshot.iter().eq(chain.iter().take(3)));
+
+    let payment = &tip.transactions()[0];
+    assert_eq!(snapshot.find_transaction(&payment.hash()).map(|(h, _)| h), Some(2));
+}
+
+#[test]
+fn test_pending_transactions_lists_mempool() {
+    let mut chain = create_test_chain(None, Some(funding_genesis())).unwrap();
+    assert!(chain.pending_transactions().is_empty());
+
+    let first = create_test_transaction();
+    let second = create_test_transaction();
+    chain.submit_transaction(second.clone()).unwrap();
+    chain.submit_transaction(first.clone()).unwrap();
+    assert_eq!(chain.pending_transactions(), vec![first, second]);
+    assert_eq!(chain.pending_transactions().len(), 2);
+
+    chain.add_block().unwrap();
+    assert!(chain.pending_transactions().is_empty());
+}
+
 #[test]
 fn test_invalid_genesis_detection() {
     // Create two chains with different genesis transactions
@@ -153,4 +260,1442 @@ fn test_invalid_genesis_detection() {
         chain1.get_block(0).unwrap().hash(),
         chain2.get_block(0).unwrap().hash()
     );
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_set_difficulty_is_kept_for_new_blocks() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    chain.set_difficulty(3);
+
+    for _ in 0..3 {
+        chain.submit_transaction(create_test_transaction()).unwrap();
+        chain.add_block().unwrap();
+    }
+
+    assert_eq!(chain.current_difficulty(), 3);
+    assert!((1..chain.len()).all(|i| chain.get_block(i).unwrap().difficulty() == 3));
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_verify_report_lists_every_defect() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    for _ in 0..3 {
+        chain.submit_transaction(create_test_transaction()).unwrap();
+        chain.add_block().unwrap();
+    }
+    assert!(chain.verify_report().is_ok());
+
+    // Block 1: zero-amount transaction, which also breaks its Merkle root
+    let zero_amount = Transaction::new(create_test_address(1), create_test_address(2), 0, 0);
+    chain.blocks.get_mut_for_testing(1).unwrap().set_transactions_for_testing(vec![zero_amount]);
+    // Block 3: valid transaction swapped in, breaking only its Merkle root
+    let swapped = Transaction::new(create_test_address(1), create_test_address(2), 50, 2);
+    chain.blocks.get_mut_for_testing(3).unwrap().set_transactions_for_testing(vec![swapped]);
+
+    let report = chain.verify_report();
+    let failures = report.failures();
+    assert_eq!(failures.len(), 3, "unexpected report: {:?}", failures);
+
+    assert_eq!((failures[0].height, failures[0].tx_index), (1, Some(0)));
+    assert!(matches!(
+        failures[0].error,
+        ChainError::BlockValidation(BlockError::TransactionError(_))
+    ));
+    assert_eq!((failures[1].height, failures[1].tx_index), (1, None));
+    assert!(matches!(
+        failures[1].error,
+        ChainError::BlockValidation(BlockError::InvalidMerkleRoot)
+    ));
+    assert_eq!((failures[2].height, failures[2].tx_index), (3, None));
+
+    // The fail-fast check still stops at the first problem
+    assert!(chain.verify().is_err());
+}
+
+fn mine_blocks<S: BlockStore>(chain: &mut Chain<S>, count: usize) {
+    for _ in 0..count {
+        chain.submit_transaction(create_test_transaction()).unwrap();
+        chain.add_block().unwrap();
+    }
+}
+
+#[test]
+fn test_chain_with_explicit_store() {
+    let mut chain = Chain::with_store(
+        MemoryBlockStore::new(),
+        1,
+        GenesisConfig { transaction: Some(funding_genesis()), genesis_difficulty: 1 },
+    )
+    .unwrap();
+    mine_blocks(&mut chain, 3);
+
+    assert_eq!(chain.len(), 4);
+    assert!(chain.verify().is_ok());
+
+    // Every block is reachable through the store by height and by hash
+    let store = chain.store();
+    assert_eq!(store.len(), chain.len());
+    for (height, block) in store.iter().enumerate() {
+        assert_eq!(chain.get_block(height), Some(block));
+        assert_eq!(store.get_by_hash(&block.hash()), Some(block));
+    }
+}
+
+#[test]
+fn test_chain_reopens_populated_store() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 2);
+    let tip = chain.latest_block().unwrap().hash();
+
+    let genesis = GenesisConfig { transaction: Some(funding_genesis()), genesis_difficulty: 1 };
+    let reopened = Chain::with_store(chain.store().clone(), 1, genesis).unwrap();
+    assert_eq!(reopened.len(), 3);
+    assert_eq!(reopened.latest_block().unwrap().hash(), tip);
+    assert!(reopened.verify().is_ok());
+}
+
+#[test]
+fn test_genesis_difficulty_separate_from_chain() {
+    let genesis = GenesisConfig { transaction: Some(funding_genesis()), genesis_difficulty: 0 };
+    let mut chain = Chain::new_with_genesis(8, genesis).unwrap();
+    mine_blocks(&mut chain, 2);
+
+    assert_eq!(chain.get_block(0).unwrap().difficulty(), 0);
+    assert_eq!(chain.get_block(1).unwrap().difficulty(), 8);
+    assert_eq!(chain.get_block(2).unwrap().difficulty(), 8);
+    assert!(chain.get_block(1).unwrap().has_valid_proof());
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_genesis_difficulty_mismatch_detected() {
+    let genesis = GenesisConfig { transaction: None, genesis_difficulty: 0 };
+    let chain = Chain::new_with_genesis(1, genesis).unwrap();
+
+    // Reopen the same blocks under a config expecting a different genesis difficulty
+    let expected = GenesisConfig { transaction: None, genesis_difficulty: 1 };
+    let reopened = Chain::with_store(chain.store().clone(), 1, expected).unwrap();
+
+    assert!(matches!(
+        reopened.verify(),
+        Err(ChainError::BlockInvalid { height: 0, source }) if matches!(*source, ChainError::InvalidGenesis)
+    ));
+}
+
+#[test]
+fn test_total_volume_excludes_genesis_issuance() {
+    let genesis_tx = Transaction::new(create_test_address(0), create_test_address(1), u64::MAX, 0);
+    let mut chain = create_test_chain(None, Some(genesis_tx)).unwrap();
+    assert_eq!(chain.total_volume(), 0);
+
+    let transfer = |amount| {
+        let nonce = next_nonce();
+        Transaction::new(create_test_address(1), create_test_address(2), amount, nonce)
+    };
+    chain.add_block_with_transactions(vec![transfer(100), transfer(250)]).unwrap();
+    // Passing the whole supply there and back totals more than a u64 holds
+    let back = Transaction::new(create_test_address(2), create_test_address(1), u64::MAX, 0);
+    chain.add_block_with_transactions(vec![transfer(u64::MAX - 350), back]).unwrap();
+
+    assert_eq!(chain.total_volume(), 2 * u64::MAX as u128);
+    assert_eq!(chain.volume_in_range(0, 2), 350);
+    assert_eq!(chain.volume_in_range(2, 3), 2 * u64::MAX as u128 - 350);
+    assert_eq!(chain.volume_in_range(3, 10), 0);
+}
+
+struct MaxAmount(u64);
+
+impl ValidationRule for MaxAmount {
+    fn check(&self, transaction: &Transaction) -> Result<(), String> {
+        if transaction.amount() > self.0 {
+            return Err(format!("amount above {}", self.0));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_validation_rule_applies_to_both_block_paths() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    chain.add_validation_rule(Arc::new(MaxAmount(1000)));
+
+    let too_large = Transaction::new(create_test_address(1), create_test_address(2), 2000, 0);
+
+    assert!(matches!(
+        chain.submit_transaction(too_large.clone()),
+        Err(ChainError::MempoolError(_))
+    ));
+    assert!(matches!(
+        chain.add_block_with_transactions(vec![too_large]),
+        Err(ChainError::BlockValidation(BlockError::TransactionError(
+            TransactionError::CustomRule(_)
+        )))
+    ));
+    assert_eq!(chain.len(), 1);
+
+    chain.add_block_with_transactions(vec![create_test_transaction()]).unwrap();
+    assert_eq!(chain.len(), 2);
+}
+
+fn create_test_blocks(count: usize) -> Vec<Block> {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, count - 1);
+    chain.store().iter().cloned().collect()
+}
+
+#[test]
+fn test_try_from_blocks_accepts_valid_chain() {
+    let blocks = create_test_blocks(4);
+    let chain = Chain::try_from_blocks(blocks.clone(), ChainConfig::default()).unwrap();
+
+    assert_eq!(chain.len(), 4);
+    assert_eq!(chain.latest_block(), blocks.last());
+    let tip_hash = blocks[3].hash();
+    assert_eq!(chain.store().get_by_hash(&tip_hash), Some(&blocks[3]));
+}
+
+#[test]
+fn test_try_from_blocks_applies_config() {
+    let config = ChainConfig::default()
+        .with_genesis_transaction(funding_genesis())
+        .with_block_reward(50);
+    let mut chain = Chain::from_config(config.clone()).unwrap();
+    chain.set_miner_address(create_test_address(9));
+    mine_blocks(&mut chain, 2);
+    let blocks: Vec<Block> = chain.iter().cloned().collect();
+
+    let rebuilt = Chain::try_from_blocks(blocks.clone(), config).unwrap();
+    assert_eq!(rebuilt.block_reward(1), 50);
+    assert_eq!(rebuilt.balance_of(&create_test_address(9)), 100);
+
+    // Without the reward the coinbases pay too much
+    assert!(matches!(
+        Chain::try_from_blocks(blocks, ChainConfig::default()),
+        Err(ChainError::BlockInvalid { height: 1, source })
+            if matches!(*source, ChainError::BlockValidation(BlockError::ExcessiveCoinbase))
+    ));
+}
+
+#[test]
+fn test_try_from_blocks_rejects_bad_link() {
+    let mut blocks = create_test_blocks(4);
+    blocks.remove(2);
+
+    assert!(matches!(
+        Chain::try_from_blocks(blocks, ChainConfig::default()),
+        Err(ChainError::BlockInvalid { height: 2, source }) if matches!(*source, ChainError::InvalidBlockLink)
+    ));
+}
+
+#[test]
+fn test_verify_rejects_duplicate_block() {
+    let mut blocks = create_test_blocks(3);
+    blocks.push(blocks[1].clone());
+
+    assert!(matches!(
+        Chain::try_from_blocks(blocks.clone(), ChainConfig::default()),
+        Err(ChainError::DuplicateBlock { height: 3 })
+    ));
+
+    let store = blocks.into_iter().fold(MemoryBlockStore::new(), |mut store, block| {
+        store.put(block);
+        store
+    });
+    let chain = Chain::with_store(store, 1, GenesisConfig { transaction: None, genesis_difficulty: 1 }).unwrap();
+    let report = chain.verify_report();
+    assert!(report
+        .failures()
+        .iter()
+        .any(|f| f.height == 3 && matches!(f.error, ChainError::DuplicateBlock { height: 3 })));
+}
+
+#[test]
+fn test_try_from_blocks_rejects_missing_genesis() {
+    let mut blocks = create_test_blocks(3);
+    blocks.remove(0);
+
+    assert!(matches!(
+        Chain::try_from_blocks(blocks, ChainConfig::default()),
+        Err(ChainError::BlockInvalid { height: 0, source }) if matches!(*source, ChainError::InvalidGenesis)
+    ));
+    assert!(matches!(
+        Chain::try_from_blocks(Vec::new(), ChainConfig::default()),
+        Err(ChainError::EmptyChain)
+    ));
+}
+
+// Continues the sender's nonces from the stored blocks, which tests sometimes
+// fill directly
+fn create_block_at(chain: &Chain, timestamp: time::OffsetDateTime) -> Block {
+    let previous_hash = chain.latest_block().unwrap().hash();
+    let sender = create_test_address(1);
+    let nonce = chain.nonces_through(chain.len() - 1).next(&sender);
+    let transaction = Transaction::new(sender, create_test_address(2), 100, nonce);
+    let mut block = Block::new(vec![transaction], previous_hash, 1).unwrap();
+    block.set_timestamp_for_testing(timestamp);
+    block.mine();
+    block
+}
+
+#[test]
+fn test_block_below_median_time_past_rejected() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 4);
+
+    let hour_ago = time::OffsetDateTime::now_utc() - time::Duration::hours(1);
+    let stale = create_block_at(&chain, hour_ago);
+    assert!(stale.verify(false).is_ok());
+    chain.blocks.put(stale);
+
+    assert!(matches!(
+        chain.verify(),
+        Err(ChainError::BlockInvalid { height: 5, source }) if matches!(*source, ChainError::TimestampBelowMedian)
+    ));
+    let report = chain.verify_report();
+    assert_eq!(report.failures().len(), 2);
+    assert!(report.failures().iter().all(|f| f.height == 5));
+    assert!(matches!(report.failures()[0].error, ChainError::TimestampBeforeParent));
+    assert!(matches!(report.failures()[1].error, ChainError::TimestampBelowMedian));
+}
+
+#[test]
+fn test_block_at_median_time_past_rejected() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    let start = chain.latest_block().unwrap().timestamp();
+    for offset in [10, 20, 20] {
+        let block = create_block_at(&chain, start + time::Duration::seconds(offset));
+        chain.blocks.put(block);
+    }
+    assert!(chain.verify().is_ok());
+
+    // The median of the four blocks so far is +20, also the parent's second
+    let at_median = create_block_at(&chain, start + time::Duration::seconds(20));
+    chain.blocks.put(at_median);
+    assert!(matches!(
+        chain.verify(),
+        Err(ChainError::BlockInvalid { height: 4, source }) if matches!(*source, ChainError::TimestampBelowMedian)
+    ));
+
+    chain.blocks.truncate(4);
+    let after_median = create_block_at(&chain, start + time::Duration::seconds(21));
+    chain.blocks.put(after_median);
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_block_above_median_time_past_accepted() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 4);
+
+    let later = time::OffsetDateTime::now_utc() + time::Duration::seconds(30);
+    let block = create_block_at(&chain, later);
+    chain.blocks.put(block);
+
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_block_size_policy_scales_with_difficulty() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    chain.set_block_size_policy(|difficulty| 2 * (difficulty as usize + 1));
+    let transactions = |count| (0..count).map(|_| create_test_transaction()).collect::<Vec<_>>();
+
+    // Difficulty 1 allows 4 transactions
+    assert!(matches!(
+        chain.add_block_with_transactions(transactions(5)),
+        Err(ChainError::BlockValidation(BlockError::TooManyTransactions))
+    ));
+    resync_nonces(&chain);
+    chain.add_block_with_transactions(transactions(4)).unwrap();
+
+    // Difficulty 2 allows 6 transactions
+    chain.set_difficulty(2);
+    chain.add_block_with_transactions(transactions(6)).unwrap();
+    assert!(matches!(
+        chain.add_block_with_transactions(transactions(7)),
+        Err(ChainError::BlockValidation(BlockError::TooManyTransactions))
+    ));
+    resync_nonces(&chain);
+
+    // The mempool path fills blocks up to the limit
+    for tx in transactions(8) {
+        chain.submit_transaction(tx).unwrap();
+    }
+    chain.add_block().unwrap();
+    assert_eq!(chain.latest_block().unwrap().transactions().len(), 6);
+    assert!(chain.verify().is_ok());
+
+    // Tightening the policy invalidates the existing blocks
+    chain.set_block_size_policy(|_| 3);
+    assert!(matches!(
+        chain.verify(),
+        Err(ChainError::BlockInvalid { height: 1, source })
+            if matches!(*source, ChainError::BlockValidation(BlockError::TooManyTransactions))
+    ));
+}
+
+#[test]
+fn test_compact_keeps_chain_contents() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 3);
+    let pending = create_test_transaction();
+    chain.submit_transaction(pending.clone()).unwrap();
+    let blocks: Vec<Block> = chain.store().iter().cloned().collect();
+    let (utxo_count, utxo_value) = (chain.utxos().len(), chain.utxos().total_value());
+    let mined = blocks[1].transactions()[0].clone();
+
+    chain.compact();
+
+    assert_eq!(chain.store().iter().cloned().collect::<Vec<_>>(), blocks);
+    assert_eq!((chain.utxos().len(), chain.utxos().total_value()), (utxo_count, utxo_value));
+    assert_eq!(chain.find_transaction(&mined.hash()), Some((1, &mined)));
+    assert!(chain.mempool.contains(&pending));
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_add_block_consumes_injected_mempool() {
+    let transactions = vec![create_test_transaction(), create_test_transaction()];
+    let mut mempool = Mempool::new();
+    for tx in &transactions {
+        mempool.add_transaction(tx.clone()).unwrap();
+    }
+
+    let mut chain = Chain::new_with_mempool(1, Some(funding_genesis()), mempool).unwrap();
+    chain.add_block().unwrap();
+
+    assert_eq!(chain.len(), 2);
+    assert_eq!(chain.latest_block().unwrap().transactions(), &transactions[..]);
+    assert!(chain.mempool.is_empty());
+}
+
+#[test]
+fn test_dust_rejected_by_policy_but_valid_in_block() {
+    let mut mempool = Mempool::new();
+    mempool.set_dust_threshold(10);
+    let mut chain = Chain::new_with_mempool(1, Some(funding_genesis()), mempool).unwrap();
+
+    let nonce = next_nonce();
+    let dust = Transaction::new(create_test_address(1), create_test_address(2), 5, nonce);
+
+    // Our own relay policy refuses it...
+    assert!(matches!(
+        chain.submit_transaction(dust.clone()),
+        Err(ChainError::MempoolError(MempoolError::InvalidTransaction(TransactionError::Dust)))
+    ));
+
+    // ...but a block containing it is still valid under consensus rules
+    chain.add_block_with_transactions(vec![dust]).unwrap();
+    assert_eq!(chain.len(), 2);
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_verify_checkpoints() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 4);
+    let hash_at = |height: usize| chain.get_block(height).unwrap().hash();
+
+    let checkpoints = [(0, hash_at(0)), (2, hash_at(2)), (4, hash_at(4))];
+    assert!(chain.verify_checkpoints(&checkpoints).is_ok());
+    assert!(chain.verify_checkpoints(&[]).is_ok());
+
+    // The lowest failing height is reported regardless of input order
+    let bad = [(4, [1; 32]), (0, hash_at(0)), (3, hash_at(2))];
+    assert!(matches!(
+        chain.verify_checkpoints(&bad),
+        Err(ChainError::CheckpointMismatch { height: 3 })
+    ));
+
+    // A checkpoint beyond the tip is not matched
+    assert!(matches!(
+        chain.verify_checkpoints(&[(10, hash_at(4))]),
+        Err(ChainError::CheckpointMismatch { height: 10 })
+    ));
+}
+
+#[test]
+fn test_export_graph() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    let funding = Transaction::new(create_test_address(1), create_test_address(7), 100, next_nonce());
+    let payment = Transaction::new(create_test_address(7), create_test_address(8), 42, 0);
+    chain.add_block_with_transactions(vec![funding, payment.clone()]).unwrap();
+
+    let edges = chain.export_graph();
+
+    // Genesis plus two transfers, two edges each
+    assert_eq!(edges.len(), 6);
+    let tx_node = GraphNode::Transaction(payment.hash());
+    assert!(edges.contains(&GraphEdge {
+        from: GraphNode::Address(create_test_address(7)),
+        to: tx_node.clone(),
+        amount: 42,
+    }));
+    assert!(edges.contains(&GraphEdge {
+        from: tx_node,
+        to: GraphNode::Address(create_test_address(8)),
+        amount: 42,
+    }));
+}
+
+// Continues from `parent` the nonces of the sender `mine_blocks` uses
+fn build_branch(parent: &Block, count: usize) -> Vec<Block> {
+    let sender = create_test_address(1);
+    let mut nonce = parent
+        .transactions()
+        .iter()
+        .rfind(|tx| tx.sender() == &sender)
+        .map_or(0, |tx| tx.nonce() + 1);
+    let mut previous_hash = parent.hash();
+    (1..=count as i64)
+        .map(|offset| {
+            let transaction = Transaction::new(sender.clone(), create_test_address(2), 50, nonce);
+            nonce += 1;
+            // A second apart, so each block is after the median of those below
+            let timestamp = parent.timestamp() + time::Duration::seconds(offset);
+            let mut block = Block::new_at(vec![transaction], previous_hash, 1, timestamp).unwrap();
+            block.mine();
+            previous_hash = block.hash();
+            block
+        })
+        .collect()
+}
+
+#[test]
+fn test_reorg_to_reports_diff() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 4);
+    let old_tip: Vec<Block> = (3..5).map(|h| chain.get_block(h).unwrap().clone()).collect();
+
+    let branch = build_branch(chain.get_block(2).unwrap(), 3);
+    let result = chain.reorg_to(branch.clone()).unwrap();
+
+    assert_eq!(result.fork_height, 2);
+    assert_eq!(result.disconnected, old_tip);
+    assert_eq!(result.connected, branch);
+    assert_eq!(chain.len(), 6);
+    assert_eq!(chain.latest_block(), branch.last());
+    assert!(chain.verify().is_ok());
+
+    // The branch used the abandoned transactions' nonces, so they are not
+    // pending again
+    for block in &old_tip {
+        assert!(!chain.mempool.contains(&block.transactions()[0]));
+    }
+    assert!(chain.mempool.is_empty());
+}
+
+#[test]
+fn test_stale_nonces_leave_the_mempool() {
+    let alice = create_test_address(1);
+    let bob = create_test_address(11);
+    let dave = create_test_address(13);
+    let mut chain = create_test_chain(None, None).unwrap();
+    chain
+        .add_block_with_transactions(vec![Transaction::new(alice.clone(), dave.clone(), 500, 0)])
+        .unwrap();
+    let dave_payment = Transaction::new(dave.clone(), bob.clone(), 10, 0);
+    chain.add_block_with_transactions(vec![dave_payment.clone()]).unwrap();
+    let pending = Transaction::new(alice.clone(), bob.clone(), 7, 1);
+    chain.submit_transaction(pending.clone()).unwrap();
+
+    // The branch spends alice's nonce 1 but not dave's nonce 0
+    let branch = build_branch(chain.get_block(1).unwrap(), 2);
+    chain.reorg_to(branch).unwrap();
+    assert!(!chain.mempool.contains(&pending));
+    assert!(chain.mempool.contains(&dave_payment));
+
+    // A block that spends dave's nonce 0 differently displaces it too
+    chain
+        .add_block_with_transactions(vec![Transaction::new(dave, bob, 20, 0)])
+        .unwrap();
+    assert!(chain.mempool.is_empty());
+}
+
+#[test]
+fn test_reorg_to_rejects_shorter_or_unlinked_branch() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 4);
+    let tip = chain.latest_block().unwrap().hash();
+
+    // Forking at height 2 with two blocks only ties the current length
+    let short = build_branch(chain.get_block(2).unwrap(), 2);
+    assert!(matches!(chain.reorg_to(short), Err(ChainError::BranchNotLonger)));
+
+    let mut unlinked = build_branch(chain.get_block(2).unwrap(), 4);
+    unlinked.remove(0);
+    assert!(matches!(chain.reorg_to(unlinked), Err(ChainError::InvalidBlockLink)));
+
+    assert_eq!(chain.len(), 5);
+    assert_eq!(chain.latest_block().unwrap().hash(), tip);
+}
+
+#[test]
+fn test_min_block_interval() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    chain.set_min_block_interval(time::Duration::seconds(60));
+    let parent_time = chain.latest_block().unwrap().timestamp();
+
+    // Mining straight after genesis is too soon
+    chain.submit_transaction(create_test_transaction()).unwrap();
+    assert!(matches!(chain.add_block(), Err(ChainError::BlockTooSoon)));
+    assert_eq!(chain.len(), 1);
+
+    for (offset, accepted) in [(59, false), (60, false), (61, true), (120, true)] {
+        let block = create_block_at(&chain, parent_time + time::Duration::seconds(offset));
+        chain.blocks.put(block);
+
+        let result = chain.verify();
+        assert_eq!(result.is_ok(), accepted, "offset {}: {:?}", offset, result);
+        if !accepted {
+            assert!(matches!(
+                result,
+                Err(ChainError::BlockInvalid { source, .. }) if matches!(*source, ChainError::BlockTooSoon)
+            ));
+        }
+        chain.blocks.truncate(1);
+    }
+}
+
+#[test]
+fn test_safe_tip() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 5);
+
+    let tip = chain.latest_block().unwrap().hash();
+    assert_eq!(chain.safe_tip(0), Some((5, tip)));
+    assert_eq!(chain.safe_tip(3), Some((2, chain.get_block(2).unwrap().hash())));
+    assert_eq!(chain.safe_tip(5), Some((0, chain.get_block(0).unwrap().hash())));
+    assert_eq!(chain.safe_tip(6), None);
+}
+
+fn temp_chain_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("dhai-chain-{}-{}.bin", std::process::id(), name))
+}
+
+#[test]
+fn test_save_and_load_round_trip() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 3);
+    chain.set_difficulty(2);
+    chain.submit_transaction(create_test_transaction()).unwrap();
+
+    let path = temp_chain_path("round-trip");
+    chain.save_to_file(&path).unwrap();
+    let loaded = Chain::load_from_file(&path, ChainConfig::default()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.len(), chain.len());
+    assert!(loaded.store().iter().eq(chain.store().iter()));
+    assert_eq!(loaded.current_difficulty(), 2);
+    assert_eq!(loaded.genesis_difficulty(), chain.genesis_difficulty());
+    assert!(loaded.mempool.is_empty());
+    assert!(loaded.verify().is_ok());
+}
+
+#[test]
+fn test_save_and_load_keep_consensus_settings() {
+    let config = ChainConfig::default()
+        .with_genesis_transaction(funding_genesis())
+        .with_block_reward(50)
+        .with_max_txs_per_block(20);
+    let mut chain = Chain::from_config(config.clone()).unwrap();
+    chain.set_miner_address(create_test_address(9));
+    mine_blocks(&mut chain, 1);
+    // More transactions than the default limit allows
+    let transactions: Vec<Transaction> = (0..15).map(|_| create_test_transaction()).collect();
+    for tx in &transactions {
+        chain.submit_transaction(tx.clone()).unwrap();
+    }
+    chain.add_block().unwrap();
+    assert_eq!(chain.latest_block().unwrap().transactions().len(), 16);
+
+    let path = temp_chain_path("consensus-settings");
+    chain.save_to_file(&path).unwrap();
+    let loaded = Chain::load_from_file(&path, config);
+    let with_defaults = Chain::load_from_file(&path, ChainConfig::default());
+    std::fs::remove_file(&path).unwrap();
+
+    let loaded = loaded.unwrap();
+    assert!(loaded.store().iter().eq(chain.store().iter()));
+    assert_eq!(loaded.block_reward(3), 50);
+    assert_eq!(loaded.balance_of(&create_test_address(9)), 100);
+    assert!(matches!(
+        with_defaults,
+        Err(ChainError::BlockInvalid { height: 1, source })
+            if matches!(*source, ChainError::BlockValidation(BlockError::ExcessiveCoinbase))
+    ));
+
+    let blocks: Vec<Block> = chain.iter().cloned().collect();
+    let limited = ChainConfig::default().with_block_reward(50);
+    assert!(matches!(
+        Chain::try_from_blocks(blocks, limited),
+        Err(ChainError::BlockInvalid { height: 2, source })
+            if matches!(*source, ChainError::BlockValidation(BlockError::TooManyTransactions))
+    ));
+}
+
+#[test]
+fn test_load_rejects_corrupt_file() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 2);
+    let path = temp_chain_path("corrupt");
+    chain.save_to_file(&path).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+
+    // Drop the middle block: both neighbours still decode but no longer link
+    let block_len = |offset: usize| u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+    let first = 12;
+    let second = first + 4 + block_len(first);
+    let third = second + 4 + block_len(second);
+    let mut unlinked = bytes[..first].to_vec();
+    unlinked[8..12].copy_from_slice(&2u32.to_be_bytes());
+    unlinked.extend_from_slice(&bytes[first..second]);
+    unlinked.extend_from_slice(&bytes[third..]);
+    std::fs::write(&path, unlinked).unwrap();
+    assert!(matches!(
+        Chain::load_from_file(&path, ChainConfig::default()),
+        Err(ChainError::BlockInvalid { height: 1, source }) if matches!(*source, ChainError::InvalidBlockLink)
+    ));
+
+    std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+    assert!(matches!(
+        Chain::load_from_file(&path, ChainConfig::default()),
+        Err(ChainError::BlockValidation(BlockError::DecodingError(_)))
+    ));
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(Chain::load_from_file(&path, ChainConfig::default()), Err(ChainError::IoError(_))));
+}
+
+#[test]
+fn test_block_info_confirmations() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 3);
+
+    let genesis = chain.block_info(0).unwrap();
+    assert_eq!(genesis.block, chain.get_block(0).unwrap());
+    assert_eq!(genesis.confirmations, 4);
+    assert_eq!(genesis.cumulative_work, 2);
+    assert_eq!(genesis.size, genesis.block.to_bytes().len());
+
+    let tip = chain.block_info(3).unwrap();
+    assert_eq!(Some(tip.block), chain.latest_block());
+    assert_eq!(tip.confirmations, 1);
+    assert_eq!(tip.cumulative_work, 8); // four blocks at difficulty 1
+
+    assert!(chain.block_info(4).is_none());
+}
+
+#[test]
+fn test_evaluate_chain_matches_reorg_outcome() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 4);
+    let tip = chain.latest_block().unwrap().hash();
+
+    // Losing: two blocks replacing two blocks is no gain in work
+    let losing = build_branch(chain.get_block(2).unwrap(), 2);
+    let evaluation = chain.evaluate_chain(&losing).unwrap();
+    assert_eq!(
+        evaluation,
+        ChainEvaluation { would_replace: false, fork_height: 2, reorg_depth: 2 }
+    );
+    assert_eq!(chain.latest_block().unwrap().hash(), tip);
+    assert!(matches!(chain.reorg_to(losing), Err(ChainError::BranchNotLonger)));
+
+    // Winning: three blocks replacing two
+    let winning = build_branch(chain.get_block(2).unwrap(), 3);
+    let evaluation = chain.evaluate_chain(&winning).unwrap();
+    assert_eq!(
+        evaluation,
+        ChainEvaluation { would_replace: true, fork_height: 2, reorg_depth: 2 }
+    );
+    assert_eq!(chain.latest_block().unwrap().hash(), tip);
+
+    let result = chain.reorg_to(winning).unwrap();
+    assert_eq!(result.fork_height, evaluation.fork_height);
+    assert_eq!(result.disconnected.len() as u64, evaluation.reorg_depth);
+}
+
+fn create_retargeting_chain(difficulty: u32) -> Chain {
+    let retarget = RetargetConfig {
+        target_block_time: time::Duration::seconds(10),
+        retarget_interval: 2,
+    };
+    Chain::new_with_config(difficulty, Some(funding_genesis()), Some(retarget), 0, None).unwrap()
+}
+
+fn put_block_at(chain: &mut Chain, timestamp: time::OffsetDateTime, difficulty: u32) {
+    let previous_hash = chain.latest_block().unwrap().hash();
+    let mut block = Block::new(vec![create_test_transaction()], previous_hash, difficulty).unwrap();
+    block.set_timestamp_for_testing(timestamp);
+    block.mine();
+    chain.blocks.put(block);
+}
+
+#[test]
+fn test_fast_blocks_raise_difficulty() {
+    let mut chain = create_retargeting_chain(1);
+    assert_eq!(chain.current_difficulty(), 1);
+
+    // Blocks mined back to back land far under the 10s target
+    mine_blocks(&mut chain, 1);
+    assert_eq!(chain.current_difficulty(), 2); // height 2 retargets
+    mine_blocks(&mut chain, 1);
+    assert_eq!(chain.get_block(2).unwrap().difficulty(), 2);
+    assert_eq!(chain.current_difficulty(), 2); // height 3 inherits
+    mine_blocks(&mut chain, 1);
+    assert_eq!(chain.current_difficulty(), 3); // height 4 retargets again
+
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_slow_blocks_lower_difficulty() {
+    let mut chain = create_retargeting_chain(3);
+    let genesis_time = chain.get_block(0).unwrap().timestamp();
+
+    put_block_at(&mut chain, genesis_time + time::Duration::seconds(100), 3);
+    // 100s for one 10s gap: height 2 drops a bit
+    put_block_at(&mut chain, genesis_time + time::Duration::seconds(110), 2);
+    put_block_at(&mut chain, genesis_time + time::Duration::seconds(120), 2);
+
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_verify_rejects_unretargeted_difficulty() {
+    let mut chain = create_retargeting_chain(3);
+    let genesis_time = chain.get_block(0).unwrap().timestamp();
+
+    put_block_at(&mut chain, genesis_time + time::Duration::seconds(100), 3);
+    put_block_at(&mut chain, genesis_time + time::Duration::seconds(110), 3);
+
+    assert!(matches!(
+        chain.verify(),
+        Err(ChainError::BlockInvalid { height: 2, source })
+            if matches!(*source, ChainError::UnexpectedDifficulty { height: 2 })
+    ));
+    assert!(chain
+        .verify_report()
+        .failures()
+        .iter()
+        .any(|f| matches!(f.error, ChainError::UnexpectedDifficulty { height: 2 })));
+}
+
+#[test]
+fn test_balances_follow_blocks() {
+    let alice = create_test_address(10);
+    let bob = create_test_address(11);
+    let genesis_tx = Transaction::new(Address::zero(), alice.clone(), 100, 0);
+    let mut chain = create_test_chain(None, Some(genesis_tx)).unwrap();
+    assert_eq!(chain.balance_of(&alice), 100);
+    assert_eq!(chain.balance_of(&bob), 0);
+
+    chain
+        .add_block_with_transactions(vec![Transaction::new(alice.clone(), bob.clone(), 40, 0)])
+        .unwrap();
+    assert_eq!(chain.balance_of(&alice), 60);
+    assert_eq!(chain.balance_of(&bob), 40);
+
+    chain.submit_transaction(Transaction::new(bob.clone(), alice.clone(), 15, 0)).unwrap();
+    chain.add_block().unwrap();
+    assert_eq!(chain.balance_of(&alice), 75);
+    assert_eq!(chain.balance_of(&bob), 25);
+    assert_eq!(chain.utxos().total_value(), 100);
+
+    // A chain rebuilt from the same blocks arrives at the same balances
+    let blocks: Vec<Block> = chain.store().iter().cloned().collect();
+    let rebuilt = Chain::try_from_blocks(blocks, ChainConfig::default()).unwrap();
+    assert_eq!(rebuilt.balance_of(&alice), 75);
+    assert_eq!(rebuilt.balance_of(&bob), 25);
+}
+
+#[test]
+fn test_next_nonce_follows_mined_and_pending() {
+    let alice = create_test_address(10);
+    let bob = create_test_address(11);
+    let genesis_tx = Transaction::new(Address::zero(), alice.clone(), 100, 0);
+    let mut chain = create_test_chain(None, Some(genesis_tx)).unwrap();
+    assert_eq!(chain.next_nonce(&alice), 0);
+
+    chain.submit_transaction(Transaction::new(alice.clone(), bob.clone(), 10, chain.next_nonce(&alice))).unwrap();
+    chain.add_block().unwrap();
+    assert_eq!(chain.next_nonce(&alice), 1);
+    assert_eq!(chain.next_nonce(&bob), 0);
+
+    // Pending transactions count too, so a wallet can queue several
+    chain.submit_transaction(Transaction::new(alice.clone(), bob.clone(), 10, 1)).unwrap();
+    assert_eq!(chain.next_nonce(&alice), 2);
+    chain.add_block().unwrap();
+    assert_eq!(chain.next_nonce(&alice), 2);
+}
+
+#[test]
+fn test_nonce_gap_waits_in_mempool() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    let payment = |nonce| Transaction::new(create_test_address(1), create_test_address(2), 100, nonce);
+
+    chain.submit_transaction(payment(0)).unwrap();
+    chain.submit_transaction(payment(2)).unwrap();
+    chain.add_block().unwrap();
+    let included: Vec<u64> = chain.latest_block().unwrap().transactions().iter().map(|tx| tx.nonce()).collect();
+    assert_eq!(included, vec![0]);
+    assert!(chain.mempool.contains(&payment(2)));
+
+    // Nor can a block be built around the gap directly
+    assert!(matches!(
+        chain.add_block_with_transactions(vec![payment(2)]),
+        Err(ChainError::NonceOutOfOrder)
+    ));
+    assert!(matches!(
+        chain.add_block_with_transactions(vec![payment(1), payment(0)]),
+        Err(ChainError::NonceOutOfOrder)
+    ));
+
+    // Once the missing nonce arrives both go in, in order
+    chain.submit_transaction(payment(1)).unwrap();
+    chain.add_block().unwrap();
+    let included: Vec<u64> = chain.latest_block().unwrap().transactions().iter().map(|tx| tx.nonce()).collect();
+    assert_eq!(included, vec![1, 2]);
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_reorg_rebuilds_balances() {
+    // `build_branch` pays from this address
+    let alice = create_test_address(1);
+    let bob = create_test_address(11);
+    let genesis_tx = Transaction::new(Address::zero(), alice.clone(), 100, 0);
+    let mut chain = create_test_chain(None, Some(genesis_tx)).unwrap();
+    chain
+        .add_block_with_transactions(vec![Transaction::new(alice.clone(), bob.clone(), 40, 0)])
+        .unwrap();
+    assert_eq!(chain.balance_of(&bob), 40);
+
+    // A longer branch from genesis that never pays bob
+    let branch = build_branch(chain.get_block(0).unwrap(), 2);
+    chain.reorg_to(branch).unwrap();
+    assert_eq!(chain.balance_of(&bob), 0);
+
+    // The balance ledger and the UTXO set still agree
+    for address in [alice, bob, create_test_address(2)] {
+        assert_eq!(chain.balance_of(&address), chain.utxos().balance_of(&address));
+    }
+    assert_eq!(chain.balance_of(&create_test_address(2)), 100);
+}
+
+#[test]
+fn test_reorg_does_not_requeue_overspends() {
+    let alice = create_test_address(1);
+    let bob = create_test_address(11);
+    let carol = create_test_address(12);
+    let genesis_tx = Transaction::new(Address::zero(), alice.clone(), 100, 0);
+    let mut chain = create_test_chain(None, Some(genesis_tx)).unwrap();
+    let overspend = Transaction::new(alice.clone(), bob.clone(), 40, 1);
+    chain
+        .add_block_with_transactions(vec![Transaction::new(alice.clone(), bob.clone(), 10, 0)])
+        .unwrap();
+    chain.add_block_with_transactions(vec![overspend.clone()]).unwrap();
+
+    // A longer branch where alice's first payment leaves her only 10
+    let payments = [
+        Transaction::new(alice.clone(), bob.clone(), 90, 0),
+        Transaction::new(bob.clone(), carol.clone(), 10, 0),
+        Transaction::new(bob.clone(), carol.clone(), 10, 1),
+    ];
+    let genesis = chain.get_block(0).unwrap();
+    let mut previous_hash = genesis.hash();
+    let branch: Vec<Block> = payments
+        .into_iter()
+        .zip(1..)
+        .map(|(transaction, offset)| {
+            let timestamp = genesis.timestamp() + time::Duration::seconds(offset);
+            let mut block = Block::new_at(vec![transaction], previous_hash, 1, timestamp).unwrap();
+            block.mine();
+            previous_hash = block.hash();
+            block
+        })
+        .collect();
+    chain.reorg_to(branch).unwrap();
+    assert!(!chain.mempool.contains(&overspend));
+
+    // One that reaches the mempool anyway is passed over rather than
+    // failing every block after it
+    chain.mempool.add_transaction(overspend.clone()).unwrap();
+    let payment = Transaction::new(bob, carol, 10, 2);
+    chain.submit_transaction(payment.clone()).unwrap();
+    chain.add_block().unwrap();
+    assert_eq!(chain.latest_block().unwrap().transactions(), std::slice::from_ref(&payment));
+    assert!(chain.mempool.contains(&overspend));
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_overspend_rejected() {
+    let alice = create_test_address(10);
+    let bob = create_test_address(11);
+    let genesis_tx = Transaction::new(Address::zero(), alice.clone(), 100, 0);
+    let mut chain = create_test_chain(None, Some(genesis_tx)).unwrap();
+
+    let overspend = Transaction::new(alice.clone(), bob.clone(), 150, 1);
+    assert!(matches!(chain.validate_transaction(&overspend), Err(ChainError::InsufficientFunds)));
+    assert!(matches!(chain.submit_transaction(overspend), Err(ChainError::InsufficientFunds)));
+    assert!(chain.mempool.is_empty());
+
+    chain.submit_transaction(Transaction::new(alice, bob, 50, 1)).unwrap();
+    assert_eq!(chain.mempool.len(), 1);
+}
+
+#[test]
+fn test_blocks_cannot_create_funds() {
+    let alice = create_test_address(1);
+    let bob = create_test_address(11);
+    let genesis_tx = Transaction::new(Address::zero(), alice.clone(), 100, 0);
+    let mut chain = create_test_chain(None, Some(genesis_tx)).unwrap();
+    let pay = |from: &Address, to: &Address, amount, nonce| Transaction::new(from.clone(), to.clone(), amount, nonce);
+    let overspend = vec![pay(&alice, &bob, 60, 0), pay(&alice, &bob, 60, 1)];
+    let mut block = Block::new(overspend.clone(), chain.genesis_hash(), 1).unwrap();
+    block.mine();
+
+    // Rejected as a branch...
+    assert!(matches!(chain.reorg_to(vec![block.clone()]), Err(ChainError::InsufficientFunds)));
+    let candidate = vec![chain.get_block(0).unwrap().clone(), block.clone()];
+    assert!(matches!(chain.try_replace(candidate.clone()), Err(ChainError::InsufficientFunds)));
+    assert!(matches!(
+        Chain::try_from_blocks(candidate, ChainConfig::default()),
+        Err(ChainError::BlockInvalid { height: 1, source }) if matches!(*source, ChainError::InsufficientFunds)
+    ));
+
+    // ...at the tip, and when found in the store
+    assert!(matches!(
+        chain.add_block_with_transactions(overspend),
+        Err(ChainError::InsufficientFunds)
+    ));
+    chain.blocks.put(block);
+    assert!(matches!(
+        chain.verify(),
+        Err(ChainError::BlockInvalid { height: 1, source }) if matches!(*source, ChainError::InsufficientFunds)
+    ));
+    assert!(matches!(chain.verify_report().failures()[0].error, ChainError::InsufficientFunds));
+    chain.blocks.truncate(1);
+
+    // Funds received earlier in the same block can be spent
+    let relay = vec![pay(&alice, &bob, 60, 0), pay(&bob, &alice, 60, 0), pay(&alice, &bob, 100, 1)];
+    chain.add_block_with_transactions(relay).unwrap();
+    assert_eq!(chain.balance_of(&bob), 100);
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_pending_spends_count_against_balance() {
+    let alice = create_test_address(10);
+    let bob = create_test_address(11);
+    let genesis_tx = Transaction::new(Address::zero(), alice.clone(), 100, 0);
+    let mut chain = create_test_chain(None, Some(genesis_tx)).unwrap();
+
+    chain.submit_transaction(Transaction::new(alice.clone(), bob.clone(), 60, 0)).unwrap();
+    let second = Transaction::new(alice.clone(), bob.clone(), 60, 1);
+    assert!(matches!(chain.submit_transaction(second), Err(ChainError::InsufficientFunds)));
+    assert_eq!(chain.mempool.len(), 1);
+
+    // What is left after the pending spend can still be sent
+    chain.submit_transaction(Transaction::new(alice.clone(), bob.clone(), 40, 1)).unwrap();
+    chain.add_block().unwrap();
+    assert_eq!(chain.balance_of(&alice), 0);
+    assert_eq!(chain.balance_of(&bob), 100);
+    assert!(chain.verify().is_ok());
+}
+
+#[test]
+fn test_coinbase_pays_halving_reward() {
+    let miner = create_test_address(20);
+    let mut chain = Chain::new_with_config(1, Some(funding_genesis()), None, 50, Some(2)).unwrap();
+    chain.set_miner_address(miner.clone());
+    assert_eq!(chain.block_reward(1), 50);
+    assert_eq!(chain.block_reward(2), 25);
+    assert_eq!(chain.block_reward(200), 0);
+
+    mine_blocks(&mut chain, 1);
+    let block = chain.latest_block().unwrap();
+    assert_eq!(block.transactions().len(), 2);
+    assert_eq!(block.coinbase().unwrap().receiver(), &miner);
+    assert_eq!(chain.balance_of(&miner), 50);
+
+    mine_blocks(&mut chain, 1);
+    assert_eq!(chain.balance_of(&miner), 75);
+    assert!(chain.verify().is_ok());
+    assert_eq!(chain.total_volume(), 200); // the two transfers, not the rewards
+}
+
+#[test]
+fn test_coinbase_above_reward_rejected() {
+    let miner = create_test_address(20);
+    let mut chain = Chain::new_with_config(1, Some(funding_genesis()), None, 50, None).unwrap();
+    let coinbase = |amount| Transaction::new(Address::zero(), miner.clone(), amount, 1);
+    let payment = create_test_transaction();
+
+    assert!(matches!(
+        chain.add_block_with_transactions(vec![coinbase(51), payment.clone()]),
+        Err(ChainError::BlockValidation(BlockError::ExcessiveCoinbase))
+    ));
+    assert!(matches!(
+        chain.add_block_with_transactions(vec![payment.clone(), coinbase(50)]),
+        Err(ChainError::BlockValidation(BlockError::MisplacedCoinbase))
+    ));
+    chain.add_block_with_transactions(vec![coinbase(50), payment]).unwrap();
+    assert_eq!(chain.balance_of(&miner), 50);
+
+    // Without a configured reward no coinbase is allowed at all
+    let mut chain = create_test_chain(None, None).unwrap();
+    assert!(matches!(
+        chain.add_block_with_transactions(vec![coinbase(1)]),
+        Err(ChainError::BlockValidation(BlockError::ExcessiveCoinbase))
+    ));
+}
+
+#[test]
+fn test_iter_matches_indexed_access() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 3);
+
+    let hashes: Vec<[u8; 32]> = chain.iter().map(|block| block.hash()).collect();
+    let indexed: Vec<[u8; 32]> = (0..chain.len()).map(|i| chain.get_block(i).unwrap().hash()).collect();
+    assert_eq!(hashes, indexed);
+
+    let mut count = 0;
+    for block in &chain {
+        assert_eq!(Some(block), chain.get_block(count));
+        count += 1;
+    }
+    assert_eq!(count, chain.len());
+    assert_eq!(chain.iter().map(|block| block.transactions().len()).sum::<usize>(), 4);
+}
+
+#[test]
+fn test_try_replace_takes_only_longer_valid_chains() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 2);
+    let original: Vec<Block> = chain.iter().cloned().collect();
+    let genesis = chain.get_block(0).unwrap().clone();
+
+    // Shorter, and equally long, candidates are ignored
+    let mut shorter = vec![genesis.clone()];
+    shorter.extend(build_branch(&genesis, 1));
+    assert!(!chain.try_replace(shorter).unwrap());
+    let mut same_length = vec![genesis.clone()];
+    same_length.extend(build_branch(&genesis, 2));
+    assert!(!chain.try_replace(same_length).unwrap());
+
+    // A longer candidate with a tampered block is rejected
+    let mut invalid = vec![genesis.clone()];
+    invalid.extend(build_branch(&genesis, 4));
+    invalid[2].set_transactions_for_testing(vec![create_test_transaction()]);
+    assert!(matches!(
+        chain.try_replace(invalid),
+        Err(ChainError::BlockValidation(BlockError::InvalidMerkleRoot))
+    ));
+
+    // So is one built on another genesis
+    let other_genesis = Transaction::new(Address::zero(), create_test_address(9), 5, 0);
+    let mut other = vec![create_test_chain(None, Some(other_genesis)).unwrap().get_block(0).unwrap().clone()];
+    other.extend(build_branch(&other[0], 4));
+    assert!(matches!(chain.try_replace(other), Err(ChainError::InvalidGenesis)));
+    assert_eq!(chain.iter().cloned().collect::<Vec<_>>(), original);
+
+    // A longer valid candidate replaces the chain
+    let mut longer = vec![genesis.clone()];
+    longer.extend(build_branch(&genesis, 3));
+    assert!(chain.try_replace(longer.clone()).unwrap());
+    assert_eq!(chain.iter().cloned().collect::<Vec<_>>(), longer);
+    assert!(chain.verify().is_ok());
+
+    // Extending the current chain keeps the shared blocks
+    let mut extended = longer.clone();
+    extended.extend(build_branch(longer.last().unwrap(), 1));
+    assert!(chain.try_replace(extended).unwrap());
+    assert_eq!(chain.len(), 5);
+}
+
+#[test]
+fn test_backwards_timestamp_rejected() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 1);
+    let parent_time = chain.latest_block().unwrap().timestamp();
+
+    // Still above the median of the earlier blocks, but before its parent
+    let mut chain_with_gap = create_test_chain(None, None).unwrap();
+    let start = chain_with_gap.latest_block().unwrap().timestamp();
+    for offset in [10, 20, 60] {
+        let block = create_block_at(&chain_with_gap, start + time::Duration::seconds(offset));
+        chain_with_gap.blocks.put(block);
+    }
+    let backwards = create_block_at(&chain_with_gap, start + time::Duration::seconds(30));
+    chain_with_gap.blocks.put(backwards);
+    assert!(matches!(
+        chain_with_gap.verify(),
+        Err(ChainError::BlockInvalid { height: 4, source }) if matches!(*source, ChainError::TimestampBeforeParent)
+    ));
+    let report = chain_with_gap.verify_report();
+    assert_eq!(report.failures().len(), 1);
+    assert_eq!(report.failures()[0].height, 4);
+    assert!(matches!(report.failures()[0].error, ChainError::TimestampBeforeParent));
+
+    // A block in the same second as its parent passes the parent check,
+    // but with two blocks below it the median is that parent
+    let same = create_block_at(&chain, parent_time);
+    assert!(chain.check_timestamp(chain.latest_block().unwrap(), &same).is_ok());
+    assert!(matches!(chain.check_next_block(&same), Err(ChainError::TimestampBelowMedian)));
+}
+
+#[test]
+fn test_future_timestamp_rejected() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    let now = time::OffsetDateTime::now_utc();
+
+    let far = create_block_at(&chain, now + time::Duration::hours(3));
+    assert!(matches!(chain.check_next_block(&far), Err(ChainError::TimestampInFuture)));
+    let near = create_block_at(&chain, now + time::Duration::hours(1));
+    assert!(chain.check_next_block(&near).is_ok());
+
+    chain.set_max_future_drift(time::Duration::minutes(10));
+    assert!(matches!(chain.check_next_block(&near), Err(ChainError::TimestampInFuture)));
+}
+
+#[test]
+fn test_lookup_blocks_by_hash() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 4);
+
+    for (height, block) in chain.iter().enumerate() {
+        assert_eq!(chain.get_block_by_hash(&block.hash()), Some(block));
+        assert_eq!(chain.height_of(&block.hash()), Some(height));
+    }
+    assert!(chain.get_block_by_hash(&[7; 32]).is_none());
+    assert!(chain.height_of(&[7; 32]).is_none());
+}
+
+#[test]
+fn test_find_transaction() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    mine_blocks(&mut chain, 2);
+    let transaction = create_test_transaction();
+    chain.submit_transaction(transaction.clone()).unwrap();
+    chain.add_block().unwrap();
+
+    let (height, found) = chain.find_transaction(&transaction.hash()).unwrap();
+    assert_eq!(height, 3);
+    assert_eq!(found, &transaction);
+    let genesis_tx = &chain.get_block(0).unwrap().transactions()[0];
+    assert_eq!(chain.find_transaction(&genesis_tx.hash()).map(|(h, _)| h), Some(0));
+    assert!(chain.find_transaction(&[3; 32]).is_none());
+
+    // Still found after the chain is rebuilt from its blocks
+    let blocks: Vec<Block> = chain.iter().cloned().collect();
+    let rebuilt = Chain::try_from_blocks(blocks, ChainConfig::default()).unwrap();
+    assert_eq!(rebuilt.find_transaction(&transaction.hash()).map(|(h, _)| h), Some(3));
+}
+
+#[test]
+fn test_from_config() {
+    let config = ChainConfig::default().with_difficulty(4).with_block_reward(50);
+    let chain = Chain::from_config(config).unwrap();
+    assert_eq!(chain.current_difficulty(), 4);
+    assert_eq!(chain.genesis_difficulty(), 4);
+    assert_eq!(chain.block_reward(1), 50);
+    assert!(chain.verify().is_ok());
+
+    let config = ChainConfig::default()
+        .with_genesis_transaction(funding_genesis())
+        .with_genesis_difficulty(0)
+        .with_halving_interval(10)
+        .with_block_reward(8)
+        .with_mempool_capacity(1);
+    let mut chain = Chain::from_config(config).unwrap();
+    assert_eq!(chain.genesis_difficulty(), 0);
+    assert_eq!(chain.current_difficulty(), 1);
+    assert_eq!(chain.block_reward(10), 4);
+    assert_eq!(chain.get_block(0).unwrap().transactions(), &[funding_genesis()]);
+
+    chain.submit_transaction(create_test_transaction()).unwrap();
+    assert!(matches!(
+        chain.submit_transaction(create_test_transaction()),
+        Err(ChainError::MempoolError(MempoolError::MempoolFull))
+    ));
+}
+
+#[test]
+fn test_genesis_hash_pins_genesis_block() {
+    let config = ChainConfig::default().with_genesis_timestamp(datetime!(2024-01-01 0:00 UTC));
+    let expected = Chain::from_config(config.clone()).unwrap().genesis_hash();
+
+    // The same timestamp rebuilds the same genesis block
+    let chain = Chain::from_config(config.clone().with_genesis_hash(expected)).unwrap();
+    assert_eq!(chain.genesis_hash(), expected);
+    assert_eq!(chain.genesis_hash(), chain.get_block(0).unwrap().hash());
+
+    assert!(matches!(
+        Chain::from_config(config.with_genesis_hash([0xab; 32])),
+        Err(ChainError::InvalidGenesis)
+    ));
+}
+
+#[test]
+fn test_max_txs_per_block() {
+    let config = ChainConfig::default()
+        .with_genesis_transaction(funding_genesis())
+        .with_max_txs_per_block(5);
+    let mut chain = Chain::from_config(config).unwrap();
+    for _ in 0..15 {
+        chain.submit_transaction(create_test_transaction()).unwrap();
+    }
+
+    chain.add_block().unwrap();
+    assert_eq!(chain.latest_block().unwrap().transactions().len(), 5);
+    assert_eq!(chain.mempool.len(), 10);
+
+    let oversized: Vec<Transaction> = (0..6).map(|_| create_test_transaction()).collect();
+    assert!(matches!(
+        chain.add_block_with_transactions(oversized.clone()),
+        Err(ChainError::BlockValidation(BlockError::TooManyTransactions))
+    ));
+
+    // A hand-built block over the limit fails verification
+    let previous_hash = chain.latest_block().unwrap().hash();
+    let mut block = Block::new(oversized, previous_hash, 1).unwrap();
+    block.mine();
+    chain.blocks.put(block);
+    assert!(matches!(
+        chain.verify(),
+        Err(ChainError::BlockInvalid { source, .. })
+            if matches!(*source, ChainError::BlockValidation(BlockError::TooManyTransactions))
+    ));
+}
+
+#[test]
+fn test_max_block_bytes_limits_transactions() {
+    let pending: Vec<Transaction> = (0..8).map(|_| create_test_transaction()).collect();
+    // Room for three unsigned transactions, well under the count limit
+    let budget = Block::encoded_len_of(&pending[..3]) + 10;
+    let config = ChainConfig::default()
+        .with_genesis_transaction(funding_genesis())
+        .with_max_block_bytes(budget);
+    let mut chain = Chain::from_config(config).unwrap();
+    for tx in &pending {
+        chain.submit_transaction(tx.clone()).unwrap();
+    }
+
+    chain.add_block().unwrap();
+    let block = chain.latest_block().unwrap();
+    assert_eq!(block.transactions(), &pending[..3]);
+    assert!(block.encoded_len() <= budget);
+
+    assert!(matches!(
+        chain.add_block_with_transactions(pending[3..7].to_vec()),
+        Err(ChainError::BlockValidation(BlockError::BlockTooLarge))
+    ));
+}
+
+#[test]
+fn test_oversized_transaction_does_not_stall_blocks() {
+    let budget = Block::encoded_len_of(&[create_test_transaction()]) + 16;
+    let config = ChainConfig::default()
+        .with_genesis_transaction(funding_genesis())
+        .with_max_block_bytes(budget);
+    let mut chain = Chain::from_config(config).unwrap();
+
+    resync_nonces(&chain);
+    let oversized = create_test_transaction().with_data(vec![0; 64]);
+    assert!(matches!(
+        chain.submit_transaction(oversized.clone()),
+        Err(ChainError::BlockValidation(BlockError::BlockTooLarge))
+    ));
+    assert!(chain.mempool.is_empty());
+
+    // One that got into the mempool anyway is reported, not silently kept
+    chain.mempool.add_transaction(oversized).unwrap();
+    assert!(matches!(
+        chain.add_block(),
+        Err(ChainError::BlockValidation(BlockError::BlockTooLarge))
+    ));
+    assert_eq!(chain.len(), 1);
+}
+
+#[test]
+fn test_max_data_len_limits_payloads() {
+    let config = ChainConfig::default()
+        .with_genesis_transaction(funding_genesis())
+        .with_max_data_len(4);
+    let mut chain = Chain::from_config(config).unwrap();
+
+    let oversized = create_test_transaction().with_data(b"too long".to_vec());
+    assert!(matches!(
+        chain.submit_transaction(oversized.clone()),
+        Err(ChainError::MempoolError(MempoolError::InvalidTransaction(
+            TransactionError::DataTooLarge { len: 8, max: 4 }
+        )))
+    ));
+    assert!(matches!(
+        chain.add_block_with_transactions(vec![oversized]),
+        Err(ChainError::BlockValidation(BlockError::TransactionError(
+            TransactionError::DataTooLarge { .. }
+        )))
+    ));
+
+    resync_nonces(&chain);
+    let memo = create_test_transaction().with_data(b"memo".to_vec());
+    chain.submit_transaction(memo.clone()).unwrap();
+    chain.add_block().unwrap();
+    assert_eq!(chain.latest_block().unwrap().transactions(), &[memo]);
+}
+
+#[derive(Default)]
+struct CallbackCounts {
+    blocks: AtomicUsize,
+    transactions: AtomicUsize,
+    reorgs: AtomicUsize,
+    last_reorg_len: AtomicUsize,
+}
+
+struct RecordingObserver(Arc<CallbackCounts>);
+
+impl ChainObserver for RecordingObserver {
+    fn on_block_added(&self, _block: &Block) {
+        self.0.blocks.fetch_add(1, AtomicOrdering::SeqCst);
+    }
+
+    fn on_transaction_submitted(&self, _transaction: &Transaction) {
+        self.0.transactions.fetch_add(1, AtomicOrdering::SeqCst);
+    }
+
+    fn on_reorg(&self, new_len: usize) {
+        self.0.reorgs.fetch_add(1, AtomicOrdering::SeqCst);
+        self.0.last_reorg_len.store(new_len, AtomicOrdering::SeqCst);
+    }
+}
+
+#[test]
+fn test_observer_sees_blocks_transactions_and_reorgs() {
+    let mut chain = create_test_chain(None, None).unwrap();
+    let counts = Arc::new(CallbackCounts::default());
+    chain.set_observer(Box::new(RecordingObserver(Arc::clone(&counts))));
+
+    mine_blocks(&mut chain, 3);
+    chain.add_block_with_transactions(vec![create_test_transaction()]).unwrap();
+
+    // A rejected submission is not reported
+    let pending = create_test_transaction();
+    chain.submit_transaction(pending.clone()).unwrap();
+    assert!(chain.submit_transaction(pending).is_err());
+
+    assert_eq!(counts.transactions.load(AtomicOrdering::SeqCst), 4);
+    assert_eq!(counts.blocks.load(AtomicOrdering::SeqCst), 4);
+    assert_eq!(counts.reorgs.load(AtomicOrdering::SeqCst), 0);
+
+    let branch = build_branch(chain.get_block(2).unwrap(), 4);
+    chain.reorg_to(branch).unwrap();
+    assert_eq!(counts.reorgs.load(AtomicOrdering::SeqCst), 1);
+    assert_eq!(counts.last_reorg_len.load(AtomicOrdering::SeqCst), 7);
+    assert_eq!(counts.blocks.load(AtomicOrdering::SeqCst), 4);
+}
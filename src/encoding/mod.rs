@@ -0,0 +1,83 @@
+//! Byte encodings shared by hashing and serialization.
+//!
+//! Every number that feeds a hash goes through one of these helpers so the
+//! block hash, transaction hash and any wire format agree on a single,
+//! big-endian representation.
+
+use alloc::format;
+use alloc::string::String;
+use time::OffsetDateTime;
+
+/// Encodes a `u64` as 8 big-endian bytes
+pub fn encode_u64(value: u64) -> [u8; 8] {
+    value.to_be_bytes()
+}
+
+/// Encodes a transaction amount; amounts share the `u64` encoding
+pub fn encode_amount(amount: u64) -> [u8; 8] {
+    encode_u64(amount)
+}
+
+/// Encodes a timestamp as its Unix time in seconds, 8 big-endian bytes
+pub fn encode_timestamp(timestamp: OffsetDateTime) -> [u8; 8] {
+    timestamp.unix_timestamp().to_be_bytes()
+}
+
+/// Lowercase hex, two characters per byte
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parses hex (either case) into exactly `N` bytes
+pub fn from_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != N * 2 || !s.is_ascii() {
+        return None;
+    }
+
+    let mut bytes = [0; N];
+    for (byte, pair) in bytes.iter_mut().zip(s.as_bytes().chunks(2)) {
+        let pair = core::str::from_utf8(pair).ok()?;
+        *byte = u8::from_str_radix(pair, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// Serde adapter writing a 32-byte hash as a hex string
+#[cfg(feature = "serde")]
+pub mod serde_hash {
+    use alloc::string::String;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::to_hex(hash))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::from_hex(&s).ok_or_else(|| D::Error::custom("expected 64 hex characters"))
+    }
+}
+
+/// Serde adapter writing a timestamp as Unix seconds
+#[cfg(feature = "serde")]
+pub mod serde_unix_timestamp {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use time::OffsetDateTime;
+
+    pub fn serialize<S: Serializer>(
+        timestamp: &OffsetDateTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(timestamp.unix_timestamp())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<OffsetDateTime, D::Error> {
+        let seconds = i64::deserialize(deserializer)?;
+        OffsetDateTime::from_unix_timestamp(seconds).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,65 @@
+use super::*;
+use crate::block::Block;
+use crate::transaction::{Address, Transaction};
+use crate::hash::{ChainHasher, Hasher};
+
+fn create_test_transaction() -> Transaction {
+    Transaction::new(
+        Address::new([1; 20]),  // sender
+        Address::new([2; 20]),  // receiver
+        0x0102_0304_0506_0708,  // amount
+        42,                     // nonce
+    )
+}
+
+#[test]
+fn test_encode_u64_is_big_endian() {
+    assert_eq!(encode_u64(0x0102_0304_0506_0708), [1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(encode_amount(0x0102_0304_0506_0708), encode_u64(0x0102_0304_0506_0708));
+}
+
+#[test]
+fn test_transaction_hash_uses_shared_encoding() {
+    let tx = create_test_transaction();
+
+    let mut input = Vec::new();
+    input.extend_from_slice(tx.sender().as_bytes());
+    input.extend_from_slice(tx.receiver().as_bytes());
+    input.extend_from_slice(&encode_amount(tx.amount()));
+    input.extend_from_slice(&encode_u64(tx.nonce()));
+    let expected = ChainHasher::hash(&input);
+
+    assert_eq!(tx.hash(), expected);
+}
+
+#[test]
+fn test_block_hash_uses_shared_encoding() {
+    let tx = create_test_transaction();
+    let block = Block::new(vec![tx.clone()], [7; 32], 1).unwrap();
+
+    let mut input = Vec::new();
+    input.extend_from_slice(&encode_timestamp(block.timestamp()));
+    input.extend_from_slice(&block.previous_hash());
+    input.extend_from_slice(&tx.hash()); // a single transaction is its own Merkle root
+    input.extend_from_slice(&block.difficulty().to_be_bytes());
+    input.extend_from_slice(&encode_u64(block.nonce()));
+    let expected = ChainHasher::hash(&input);
+
+    assert_eq!(block.calculate_hash(), expected);
+}
+
+#[test]
+fn test_hex_round_trip() {
+    let bytes = [0x00, 0x0f, 0xa0, 0xff];
+    assert_eq!(to_hex(&bytes), "000fa0ff");
+    assert_eq!(from_hex::<4>("000fa0ff"), Some(bytes));
+    assert_eq!(from_hex::<4>("000FA0FF"), Some(bytes));
+}
+
+#[test]
+fn test_from_hex_rejects_malformed_input() {
+    assert_eq!(from_hex::<4>("000fa0f"), None);   // odd length
+    assert_eq!(from_hex::<4>("000fa0ff00"), None); // too long
+    assert_eq!(from_hex::<4>("000fa0fg"), None);   // not hex
+    assert_eq!(from_hex::<2>("é0"), None);         // not ascii
+}
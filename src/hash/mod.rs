@@ -0,0 +1,48 @@
+//! The hash function behind block hashes, transaction hashes, Merkle roots
+//! and UTXO ids.
+//!
+//! `ChainHasher` is the one every node uses, picked at build time: SHA-256
+//! by default, Blake3 with the `blake3` feature. Nodes built with different
+//! hashers compute different hashes for the same data, so each rejects the
+//! other's blocks as having an invalid hash. Addresses are not affected;
+//! they are always derived with SHA-256.
+
+use sha2::{Digest, Sha256};
+
+/// A 256-bit hash function
+pub trait Hasher {
+    fn hash(data: &[u8]) -> [u8; 32];
+}
+
+/// SHA-256, the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Hasher;
+
+impl Hasher for Sha256Hasher {
+    fn hash(data: &[u8]) -> [u8; 32] {
+        Sha256::digest(data).into()
+    }
+}
+
+/// Blake3 with its default 32-byte output
+#[cfg(feature = "blake3")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3Hasher;
+
+#[cfg(feature = "blake3")]
+impl Hasher for Blake3Hasher {
+    fn hash(data: &[u8]) -> [u8; 32] {
+        blake3::hash(data).into()
+    }
+}
+
+/// The hasher this build commits to
+#[cfg(not(feature = "blake3"))]
+pub type ChainHasher = Sha256Hasher;
+
+/// The hasher this build commits to
+#[cfg(feature = "blake3")]
+pub type ChainHasher = Blake3Hasher;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,25 @@
+use super::*;
+use crate::encoding::to_hex;
+
+#[test]
+fn test_sha256_known_vector() {
+    assert_eq!(
+        to_hex(&Sha256Hasher::hash(b"abc")),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+fn test_blake3_known_vector() {
+    assert_eq!(
+        to_hex(&Blake3Hasher::hash(b"abc")),
+        "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+    );
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+fn test_hashers_disagree() {
+    assert_ne!(Sha256Hasher::hash(b"block"), Blake3Hasher::hash(b"block"));
+}
@@ -1,5 +1,34 @@
+//! A small proof-of-work blockchain.
+//!
+//! The `std` feature, on by default, is needed for anything that reads the
+//! clock, draws randomness, spawns threads or touches the filesystem. With
+//! it off the crate is `#![no_std]` and only needs `alloc`; what remains is
+//! enough to check data produced elsewhere:
+//!
+//! - `block`: decoding, hashing and `Block::verify`, but not `Block::new`
+//!   (which stamps the current time) or the threaded and timed miners
+//! - `transaction`: signing and validation, but not `with_random_salt`
+//! - `wallet`: signing with a given key, but not `Wallet::generate`
+//! - `encoding`, `hash` and `merkle`, unchanged
+//!
+//! `chain`, `mempool`, `store` and `utxo` hold node state and are only
+//! built with `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod block;
+#[cfg(feature = "std")]
 pub mod chain;
+pub mod encoding;
+pub mod hash;
 pub mod transaction;
+#[cfg(feature = "std")]
 pub mod mempool;
-pub mod utxo;
\ No newline at end of file
+pub mod merkle;
+#[cfg(feature = "std")]
+pub mod store;
+#[cfg(feature = "std")]
+pub mod utxo;
+pub mod wallet;
@@ -1,99 +1,285 @@
-use crate::transaction::Transaction;
-use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
+use crate::encoding::to_hex;
+use crate::transaction::{Address, Transaction, TransactionError, ValidationRule};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
 use thiserror::Error;
+use time::{Duration, OffsetDateTime};
 
 #[derive(Error, Debug)]
 pub enum MempoolError {
     #[error("Transaction already exists")]
     DuplicateTransaction,
-    #[error("Invalid transaction")]
-    InvalidTransaction,
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(#[from] TransactionError),
+    #[error("Sender already has a pending transaction with this nonce")]
+    NonceConflict,
+    #[error("Mempool is full of higher-priority transactions")]
+    MempoolFull,
 }
 
-// Wrapper for Transaction to implement Ord for the priority queue
-#[derive(Debug, Clone, Eq, PartialEq)]
-struct PrioritizedTransaction(Transaction);
-
-impl Ord for PrioritizedTransaction {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // Reverse comparison for min-heap (lower nonce = higher priority)
-        other.0.nonce().cmp(&self.0.nonce())
-    }
+// A pending transaction and when this node first saw it
+#[derive(Debug, Clone)]
+struct MempoolEntry {
+    transaction: Transaction,
+    received_at: OffsetDateTime,
 }
 
-impl PartialOrd for PrioritizedTransaction {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
+// What one sender has pending, kept up to date so per-sender queries do not
+// scan the whole pool
+#[derive(Debug, Clone, Default)]
+struct SenderPending {
+    nonces: BTreeSet<u64>, // Nonce of each pending transaction
+    outflow: u128,         // Their total amount, exact so removals can subtract it
 }
 
 pub struct Mempool {
-    transactions: HashMap<[u8; 32], Transaction>,
-    priority_queue: BinaryHeap<PrioritizedTransaction>,
+    transactions: HashMap<[u8; 32], MempoolEntry>,
+    // (nonce, hash) of every pending transaction, so the highest priority
+    // (lowest nonce) comes first and any entry can be removed in O(log n)
+    priority_queue: BTreeSet<(u64, [u8; 32])>,
+    senders: HashMap<Address, SenderPending>, // Pending nonces and outflow of each sender
+    rules: Vec<Arc<dyn ValidationRule>>,
+    dust_threshold: u64,
+    capacity: Option<usize>, // Most transactions held at once, if bounded
 }
 
 impl Mempool {
     pub fn new() -> Self {
         Self {
             transactions: HashMap::new(),
-            priority_queue: BinaryHeap::new(),
+            priority_queue: BTreeSet::new(),
+            senders: HashMap::new(),
+            rules: Vec::new(),
+            dust_threshold: 0,
+            capacity: None,
+        }
+    }
+
+    /// Creates a mempool holding at most `max` transactions. When full, an
+    /// incoming transaction evicts the lowest-priority one (the highest
+    /// nonce) if it outranks it, and is refused otherwise
+    pub fn with_capacity(max: usize) -> Self {
+        Self {
+            capacity: Some(max),
+            ..Self::new()
         }
     }
 
+    /// Refuses transactions whose amount is below `threshold`. This is relay
+    /// policy only; blocks are never rejected for containing dust
+    pub fn set_dust_threshold(&mut self, threshold: u64) {
+        self.dust_threshold = threshold;
+    }
+
+    /// Adds a rule every incoming transaction must satisfy
+    pub fn add_rule(&mut self, rule: Arc<dyn ValidationRule>) {
+        self.rules.push(rule);
+    }
+
     pub fn add_transaction(&mut self, transaction: Transaction) -> Result<(), MempoolError> {
+        self.add_transaction_at(transaction, OffsetDateTime::now_utc())
+    }
+
+    fn add_transaction_at(
+        &mut self,
+        transaction: Transaction,
+        received_at: OffsetDateTime,
+    ) -> Result<(), MempoolError> {
         let tx_hash = transaction.hash();
 
         if self.transactions.contains_key(&tx_hash) {
             return Err(MempoolError::DuplicateTransaction);
         }
 
-        if let Err(_) = transaction.validate(false) {
-            // Regular transactions are never genesis
-            return Err(MempoolError::InvalidTransaction);
+        if self
+            .senders
+            .get(transaction.sender())
+            .is_some_and(|pending| pending.nonces.contains(&transaction.nonce()))
+        {
+            return Err(MempoolError::NonceConflict);
         }
 
-        self.priority_queue
-            .push(PrioritizedTransaction(transaction.clone()));
-        self.transactions.insert(tx_hash, transaction);
+        // Regular transactions are never genesis
+        transaction.validate_with_rules(false, &self.rules)?;
+        transaction.validate_policy(self.dust_threshold)?;
+
+        if self
+            .capacity
+            .is_some_and(|max| self.transactions.len() >= max)
+        {
+            self.make_room_for(&transaction)?;
+        }
+
+        let pending = self.senders.entry(transaction.sender().clone()).or_default();
+        pending.nonces.insert(transaction.nonce());
+        pending.outflow += u128::from(transaction.amount());
+        self.priority_queue.insert((transaction.nonce(), tx_hash));
+        self.transactions.insert(
+            tx_hash,
+            MempoolEntry {
+                transaction,
+                received_at,
+            },
+        );
 
         Ok(())
     }
 
-    pub fn get_transactions(&self, limit: usize) -> Vec<Transaction> {
-        let all_txs = self
+    /// Evicts the lowest-priority transaction if `incoming` strictly
+    /// outranks it. Ties between equal nonces go to the smaller hash, as in
+    /// `peek`, so the choice does not depend on insertion order
+    fn make_room_for(&mut self, incoming: &Transaction) -> Result<(), MempoolError> {
+        let (lowest_key, lowest) = self
             .priority_queue
+            .last()
+            .map(|key| (*key, self.transactions[&key.1].transaction.clone()))
+            .ok_or(MempoolError::MempoolFull)?;
+        if (incoming.nonce(), incoming.hash()) >= lowest_key {
+            return Err(MempoolError::MempoolFull);
+        }
+
+        self.remove_transactions(&[lowest]);
+        Ok(())
+    }
+
+    /// Borrows up to `limit` transactions in priority order, lowest nonce
+    /// first; equal nonces are ordered by hash. Nothing is removed
+    pub fn peek(&self, limit: usize) -> Vec<&Transaction> {
+        self.priority_queue
             .iter()
-            .map(|pt| pt.0.clone())
-            .collect::<Vec<_>>();
+            .take(limit)
+            .map(|(_, hash)| &self.transactions[hash].transaction)
+            .collect()
+    }
 
-        let mut sorted = all_txs;
-        sorted.sort_by_key(|tx| tx.nonce());
-        sorted.into_iter().take(limit).collect()
+    /// Returns up to `limit` transactions in the order `peek` gives
+    pub fn get_transactions(&self, limit: usize) -> Vec<Transaction> {
+        self.peek(limit).into_iter().cloned().collect()
     }
 
     pub fn remove_transactions(&mut self, transactions: &[Transaction]) {
         for tx in transactions {
             let tx_hash = tx.hash();
-            self.transactions.remove(&tx_hash);
-            // Note: This is inefficient as we're rebuilding the heap
-            // In a real implementation, we might want a better data structure
-            self.priority_queue = self
-                .priority_queue
-                .iter()
-                .filter(|pt| pt.0.hash() != tx_hash)
-                .cloned()
-                .collect();
+            if self.transactions.remove(&tx_hash).is_none() {
+                continue;
+            }
+            if let Some(pending) = self.senders.get_mut(tx.sender()) {
+                pending.nonces.remove(&tx.nonce());
+                pending.outflow -= u128::from(tx.amount());
+                if pending.nonces.is_empty() {
+                    self.senders.remove(tx.sender());
+                }
+            }
+            self.priority_queue.remove(&(tx.nonce(), tx_hash));
         }
     }
 
+    /// Removes every pending transaction `keep` returns false for
+    pub fn retain(&mut self, mut keep: impl FnMut(&Transaction) -> bool) {
+        let dropped: Vec<Transaction> = self
+            .transactions
+            .values()
+            .map(|entry| &entry.transaction)
+            .filter(|tx| !keep(tx))
+            .cloned()
+            .collect();
+        self.remove_transactions(&dropped);
+    }
+
+    /// Returns every pending transaction ordered by hash, so identical
+    /// mempools always produce identical snapshots
+    pub fn snapshot(&self) -> Vec<Transaction> {
+        let mut entries: Vec<_> = self.transactions.iter().collect();
+        entries.sort_unstable_by_key(|(hash, _)| **hash);
+        entries
+            .into_iter()
+            .map(|(_, entry)| entry.transaction.clone())
+            .collect()
+    }
+
+    /// Returns up to `max_count` transactions that have waited at least
+    /// `min_age` by `now`, oldest first, for re-announcing to peers
+    pub fn rebroadcast_candidates(
+        &self,
+        max_count: usize,
+        now: OffsetDateTime,
+        min_age: Duration,
+    ) -> Vec<Transaction> {
+        let mut stale: Vec<_> = self
+            .transactions
+            .iter()
+            .filter(|(_, entry)| now - entry.received_at >= min_age)
+            .collect();
+        stale.sort_unstable_by_key(|(hash, entry)| (entry.received_at, **hash));
+
+        stale
+            .into_iter()
+            .take(max_count)
+            .map(|(_, entry)| entry.transaction.clone())
+            .collect()
+    }
+
+    /// Renders the pending transactions in selection order, one per line,
+    /// for diagnosing why a transaction is not being mined
+    pub fn debug_dump(&self) -> String {
+        self.debug_dump_at(OffsetDateTime::now_utc())
+    }
+
+    /// `debug_dump` with each transaction's wait measured up to `now`
+    fn debug_dump_at(&self, now: OffsetDateTime) -> String {
+        self.priority_queue
+            .iter()
+            .map(|(_, hash)| &self.transactions[hash])
+            .enumerate()
+            .map(|(position, entry)| {
+                let tx = &entry.transaction;
+                format!(
+                    "{:>4} id={} sender={} nonce={} amount={} waited={}s\n",
+                    position,
+                    to_hex(&tx.hash()[..4]),
+                    tx.sender(),
+                    tx.nonce(),
+                    tx.amount(),
+                    (now - entry.received_at).whole_seconds(),
+                )
+            })
+            .collect()
+    }
+
     pub fn contains(&self, transaction: &Transaction) -> bool {
-        self.transactions.contains_key(&transaction.hash())
+        self.contains_hash(&transaction.hash())
+    }
+
+    /// Whether the transaction with `tx_hash` is pending
+    pub fn contains_hash(&self, tx_hash: &[u8; 32]) -> bool {
+        self.transactions.contains_key(tx_hash)
+    }
+
+    /// Highest nonce among `sender`'s pending transactions
+    pub fn highest_nonce(&self, sender: &Address) -> Option<u64> {
+        self.senders
+            .get(sender)
+            .and_then(|pending| pending.nonces.last().copied())
+    }
+
+    /// Total amount `sender`'s pending transactions pay out, saturating at
+    /// `u64::MAX`
+    pub fn pending_outflow(&self, sender: &Address) -> u64 {
+        self.senders.get(sender).map_or(0, |pending| {
+            u64::try_from(pending.outflow).unwrap_or(u64::MAX)
+        })
+    }
+
+    /// Releases capacity left over from a period of heavy churn
+    pub fn shrink_to_fit(&mut self) {
+        self.transactions.shrink_to_fit();
+        self.senders.shrink_to_fit();
     }
 
     pub fn clear(&mut self) {
         self.transactions.clear();
         self.priority_queue.clear();
+        self.senders.clear();
     }
 
     pub fn len(&self) -> usize {
@@ -105,5 +291,11 @@ impl Mempool {
     }
 }
 
+impl Default for Mempool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests;
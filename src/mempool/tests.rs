@@ -1,5 +1,6 @@
 use super::*;
-use crate::transaction::{Transaction, Address};
+use crate::transaction::{Transaction, Address, ValidationRule};
+use time::{Duration, OffsetDateTime};
 
 fn create_test_address(value: u8) -> Address {
     Address::new([value; 20])
@@ -76,12 +77,50 @@ fn test_remove_transactions() {
     mempool.add_transaction(tx1.clone()).unwrap();
     mempool.add_transaction(tx2.clone()).unwrap();
     
-    mempool.remove_transactions(&[tx1.clone()]);
+    mempool.remove_transactions(std::slice::from_ref(&tx1));
     assert_eq!(mempool.len(), 1);
     assert!(!mempool.contains(&tx1));
     assert!(mempool.contains(&tx2));
 }
 
+#[test]
+fn test_per_sender_totals_follow_adds_and_removes() {
+    let mut mempool = Mempool::new();
+    let sender = create_test_address(1);
+    let transactions: Vec<Transaction> = (0..3).map(create_test_transaction).collect();
+    for tx in &transactions {
+        mempool.add_transaction(tx.clone()).unwrap();
+    }
+    assert_eq!(mempool.highest_nonce(&sender), Some(2));
+    assert_eq!(mempool.pending_outflow(&sender), 300);
+    assert_eq!(mempool.pending_outflow(&create_test_address(2)), 0);
+
+    // Removing the highest nonce falls back to the next one down
+    mempool.remove_transactions(&transactions[2..]);
+    assert_eq!(mempool.highest_nonce(&sender), Some(1));
+    assert_eq!(mempool.pending_outflow(&sender), 200);
+
+    mempool.clear();
+    assert_eq!(mempool.highest_nonce(&sender), None);
+    assert_eq!(mempool.pending_outflow(&sender), 0);
+}
+
+#[test]
+fn test_retain() {
+    let mut mempool = Mempool::new();
+    for nonce in 0..4 {
+        mempool.add_transaction(create_test_transaction(nonce)).unwrap();
+    }
+
+    mempool.retain(|tx| tx.nonce() >= 2);
+    let nonces: Vec<u64> = mempool.get_transactions(10).iter().map(|tx| tx.nonce()).collect();
+    assert_eq!(nonces, vec![2, 3]);
+
+    // The removed nonces can be used again
+    mempool.add_transaction(create_test_transaction(0)).unwrap();
+    assert_eq!(mempool.len(), 3);
+}
+
 #[test]
 fn test_clear_mempool() {
     let mut mempool = Mempool::new();
@@ -108,4 +147,259 @@ fn test_transaction_ordering() {
     // Should get all transactions in nonce order
     let transactions = mempool.get_transactions(3);
     assert_eq!(transactions, vec![tx1, tx2, tx3]);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_peek_keeps_transactions_pending() {
+    let mut mempool = Mempool::new();
+    let tx1 = create_test_transaction(1);
+    let tx2 = create_test_transaction(2);
+    let tx3 = create_test_transaction(3);
+    for tx in [&tx3, &tx1, &tx2] {
+        mempool.add_transaction(tx.clone()).unwrap();
+    }
+
+    assert_eq!(mempool.peek(2), vec![&tx1, &tx2]);
+    assert_eq!(mempool.peek(10), vec![&tx1, &tx2, &tx3]);
+    assert_eq!(mempool.len(), 3);
+
+    assert!(mempool.contains_hash(&tx2.hash()));
+    mempool.remove_transactions(std::slice::from_ref(&tx2));
+    assert!(!mempool.contains_hash(&tx2.hash()));
+    assert_eq!(mempool.peek(10), vec![&tx1, &tx3]);
+}
+
+#[test]
+fn test_debug_dump_follows_selection_order() {
+    let mut mempool = Mempool::new();
+    let now = OffsetDateTime::now_utc();
+    for nonce in [5, 2, 9, 1] {
+        let received_at = now - Duration::seconds(nonce as i64 * 10);
+        mempool.add_transaction_at(create_test_transaction(nonce), received_at).unwrap();
+    }
+
+    let dump = mempool.debug_dump_at(now);
+    let lines: Vec<&str> = dump.lines().collect();
+    let selected = mempool.get_transactions(mempool.len());
+    assert_eq!(lines.len(), selected.len());
+
+    for (line, tx) in lines.iter().zip(&selected) {
+        let id_prefix = crate::encoding::to_hex(&tx.hash()[..4]);
+        assert!(line.contains(&format!("id={}", id_prefix)), "{}", line);
+        assert!(line.contains(&format!("nonce={}", tx.nonce())), "{}", line);
+        assert!(line.contains(&format!("waited={}s", tx.nonce() * 10)), "{}", line);
+        assert!(line.contains(&format!("sender={}", tx.sender())), "{}", line);
+    }
+}
+
+struct MaxAmount(u64);
+
+impl ValidationRule for MaxAmount {
+    fn check(&self, transaction: &Transaction) -> Result<(), String> {
+        if transaction.amount() > self.0 {
+            return Err(format!("amount above {}", self.0));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_custom_rule_rejects_transaction() {
+    let mut mempool = Mempool::new();
+    mempool.add_rule(Arc::new(MaxAmount(1000)));
+
+    let too_large = Transaction::new(create_test_address(1), create_test_address(2), 2000, 1);
+    assert!(matches!(
+        mempool.add_transaction(too_large),
+        Err(MempoolError::InvalidTransaction(TransactionError::CustomRule(_)))
+    ));
+    assert!(mempool.is_empty());
+
+    mempool.add_transaction(create_test_transaction(2)).unwrap();
+    assert_eq!(mempool.len(), 1);
+}
+
+#[test]
+fn test_snapshot_is_deterministic() {
+    let transactions: Vec<_> = (0..20).map(create_test_transaction).collect();
+
+    let mut forward = Mempool::new();
+    for tx in &transactions {
+        forward.add_transaction(tx.clone()).unwrap();
+    }
+
+    // Same contents, reached through a different insertion and removal history
+    let mut shuffled = Mempool::new();
+    for tx in transactions.iter().rev() {
+        shuffled.add_transaction(tx.clone()).unwrap();
+    }
+    shuffled.add_transaction(create_test_transaction(99)).unwrap();
+    shuffled.remove_transactions(&[create_test_transaction(99)]);
+
+    let snapshot = forward.snapshot();
+    assert_eq!(snapshot.len(), transactions.len());
+    assert_eq!(snapshot, shuffled.snapshot());
+
+    let hashes: Vec<_> = snapshot.iter().map(|tx| tx.hash()).collect();
+    assert!(hashes.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_shrink_to_fit_keeps_contents() {
+    let mut mempool = Mempool::new();
+    mempool.add_transaction(create_test_transaction(1)).unwrap();
+    mempool.transactions.reserve(1000);
+    mempool.senders.reserve(1000);
+
+    mempool.shrink_to_fit();
+
+    assert!(mempool.transactions.capacity() < 1000);
+    assert!(mempool.senders.capacity() < 1000);
+    assert_eq!(mempool.get_transactions(10), vec![create_test_transaction(1)]);
+}
+
+#[test]
+fn test_rebroadcast_candidates_by_age() {
+    let mut mempool = Mempool::new();
+    let now = OffsetDateTime::now_utc();
+
+    // Received 30, 20, 10 and 1 minutes ago
+    for (nonce, minutes_ago) in [(1, 30), (2, 20), (3, 10), (4, 1)] {
+        let received_at = now - Duration::minutes(minutes_ago);
+        mempool.add_transaction_at(create_test_transaction(nonce), received_at).unwrap();
+    }
+
+    let candidates = mempool.rebroadcast_candidates(10, now, Duration::minutes(5));
+    assert_eq!(
+        candidates,
+        vec![create_test_transaction(1), create_test_transaction(2), create_test_transaction(3)]
+    );
+
+    // The cap keeps the oldest
+    let capped = mempool.rebroadcast_candidates(2, now, Duration::minutes(5));
+    assert_eq!(capped, vec![create_test_transaction(1), create_test_transaction(2)]);
+
+    assert!(mempool.rebroadcast_candidates(10, now, Duration::hours(1)).is_empty());
+}
+
+#[test]
+fn test_salted_retry_conflicts_on_nonce() {
+    let mut mempool = Mempool::new();
+    let first = create_test_transaction(1).with_salt([1; 8]);
+    let retry = create_test_transaction(1).with_salt([2; 8]);
+
+    mempool.add_transaction(first.clone()).unwrap();
+    assert!(matches!(
+        mempool.add_transaction(retry.clone()),
+        Err(MempoolError::NonceConflict)
+    ));
+    assert_eq!(mempool.len(), 1);
+    assert!(!mempool.contains(&retry));
+
+    // Once the first is mined the nonce is free in the pool again
+    mempool.remove_transactions(&[first]);
+    mempool.add_transaction(retry.clone()).unwrap();
+    assert!(mempool.contains(&retry));
+}
+
+#[test]
+fn test_same_nonce_from_other_sender_accepted() {
+    let mut mempool = Mempool::new();
+    let other = Transaction::new(create_test_address(3), create_test_address(2), 100, 1);
+
+    mempool.add_transaction(create_test_transaction(1)).unwrap();
+    mempool.add_transaction(other).unwrap();
+    assert_eq!(mempool.len(), 2);
+}
+
+#[test]
+fn test_zero_sender_rejected() {
+    let mut mempool = Mempool::new();
+    let transaction = Transaction::new(Address::zero(), create_test_address(2), 100, 1);
+
+    assert!(matches!(
+        mempool.add_transaction(transaction),
+        Err(MempoolError::InvalidTransaction(TransactionError::InvalidAddress))
+    ));
+}
+
+#[test]
+fn test_full_mempool_evicts_lowest_priority() {
+    let mut mempool = Mempool::with_capacity(3);
+    for nonce in [5, 6, 7] {
+        mempool.add_transaction(create_test_transaction(nonce)).unwrap();
+    }
+
+    // A higher-priority (lower nonce) transaction pushes out nonce 7
+    mempool.add_transaction(create_test_transaction(1)).unwrap();
+    assert_eq!(mempool.len(), 3);
+    assert!(!mempool.contains(&create_test_transaction(7)));
+    let nonces: Vec<u64> = mempool.get_transactions(10).iter().map(|tx| tx.nonce()).collect();
+    assert_eq!(nonces, vec![1, 5, 6]);
+
+    // One that ranks below everything present is refused
+    assert!(matches!(
+        mempool.add_transaction(create_test_transaction(9)),
+        Err(MempoolError::MempoolFull)
+    ));
+    assert_eq!(mempool.len(), 3);
+
+    // The evicted nonce is free again once there is room
+    mempool.remove_transactions(&[create_test_transaction(6)]);
+    mempool.add_transaction(create_test_transaction(7)).unwrap();
+    assert_eq!(mempool.len(), 3);
+}
+
+#[test]
+fn test_full_mempool_breaks_nonce_ties_by_hash() {
+    let first = Transaction::new(create_test_address(1), create_test_address(9), 100, 4);
+    let second = Transaction::new(create_test_address(2), create_test_address(9), 100, 4);
+    let (low, high) = if first.hash() < second.hash() {
+        (first, second)
+    } else {
+        (second, first)
+    };
+
+    // The smaller hash outranks the larger one at the same nonce
+    let mut mempool = Mempool::with_capacity(1);
+    mempool.add_transaction(high.clone()).unwrap();
+    mempool.add_transaction(low.clone()).unwrap();
+    assert!(mempool.contains(&low));
+    assert!(!mempool.contains(&high));
+
+    // and the larger hash cannot push it back out
+    assert!(matches!(
+        mempool.add_transaction(high),
+        Err(MempoolError::MempoolFull)
+    ));
+    assert!(mempool.contains(&low));
+}
+
+#[test]
+fn test_many_interleaved_adds_and_removes() {
+    let mut mempool = Mempool::new();
+    let transactions: Vec<Transaction> = (0..10_000).map(create_test_transaction).collect();
+    for tx in &transactions {
+        mempool.add_transaction(tx.clone()).unwrap();
+    }
+
+    // Remove every tenth transaction in batches, re-adding a few as we go
+    let start = std::time::Instant::now();
+    for batch in transactions.iter().step_by(10).collect::<Vec<_>>().chunks(100) {
+        let batch: Vec<Transaction> = batch.iter().map(|tx| (*tx).clone()).collect();
+        mempool.remove_transactions(&batch);
+        mempool.add_transaction(batch[0].clone()).unwrap();
+    }
+    let elapsed = start.elapsed();
+
+    // 1000 removed, 10 of them added back
+    assert_eq!(mempool.len(), 9_010);
+    let expected: Vec<u64> = (0..10_000)
+        .filter(|nonce| nonce % 10 != 0 || nonce % 1000 == 0)
+        .collect();
+    let nonces: Vec<u64> = mempool.get_transactions(usize::MAX).iter().map(|tx| tx.nonce()).collect();
+    assert_eq!(nonces, expected);
+    assert!(mempool.contains(&transactions[1000]));
+    assert!(!mempool.contains(&transactions[1010]));
+    assert!(elapsed < std::time::Duration::from_secs(5), "removal took {:?}", elapsed);
+}
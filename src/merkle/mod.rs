@@ -0,0 +1,78 @@
+//! Binary Merkle trees over 32-byte leaves.
+//!
+//! Each level pairs neighbouring nodes and hashes `left || right` with the
+//! chain's `ChainHasher`. A level with an odd number of nodes pairs its last
+//! node with itself.
+
+use crate::hash::{ChainHasher, Hasher};
+use alloc::vec::Vec;
+
+/// Root of the tree over `leaves`. A single leaf is its own root; no
+/// leaves give the all-zero hash
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = leaves.to_vec();
+    if level.is_empty() {
+        return [0; 32];
+    }
+
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// Sibling hashes from the leaf at `index` up to the root, each flagged
+/// `true` when the sibling is the right-hand node of its pair. `None` if
+/// `index` is out of range
+pub fn merkle_proof(leaves: &[[u8; 32]], mut index: usize) -> Option<Vec<([u8; 32], bool)>> {
+    if index >= leaves.len() {
+        return None;
+    }
+
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        let sibling_is_right = index.is_multiple_of(2);
+        let sibling = if sibling_is_right {
+            *level.get(index + 1).unwrap_or(&level[index])
+        } else {
+            level[index - 1]
+        };
+        proof.push((sibling, sibling_is_right));
+
+        level = next_level(&level);
+        index /= 2;
+    }
+    Some(proof)
+}
+
+/// Checks that `proof` leads from `leaf` to `root`
+pub fn verify_merkle_proof(leaf: [u8; 32], proof: &[([u8; 32], bool)], root: [u8; 32]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(leaf, |node, (sibling, sibling_is_right)| {
+            if *sibling_is_right {
+                hash_pair(&node, sibling)
+            } else {
+                hash_pair(sibling, &node)
+            }
+        });
+    computed == root
+}
+
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut input = [0; 64];
+    input[..32].copy_from_slice(left);
+    input[32..].copy_from_slice(right);
+    ChainHasher::hash(&input)
+}
+
+#[cfg(test)]
+mod tests;
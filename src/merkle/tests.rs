@@ -0,0 +1,61 @@
+use super::*;
+
+#[test]
+fn test_single_leaf_is_root() {
+    assert_eq!(merkle_root(&[[1; 32]]), [1; 32]);
+}
+
+#[test]
+fn test_two_leaves() {
+    assert_eq!(merkle_root(&[[1; 32], [2; 32]]), hash_pair(&[1; 32], &[2; 32]));
+}
+
+#[test]
+fn test_odd_level_duplicates_last_node() {
+    let expected = hash_pair(
+        &hash_pair(&[1; 32], &[2; 32]),
+        &hash_pair(&[3; 32], &[3; 32]),
+    );
+    assert_eq!(merkle_root(&[[1; 32], [2; 32], [3; 32]]), expected);
+}
+
+#[test]
+fn test_leaf_order_matters() {
+    assert_ne!(
+        merkle_root(&[[1; 32], [2; 32]]),
+        merkle_root(&[[2; 32], [1; 32]])
+    );
+    assert_eq!(merkle_root(&[]), [0; 32]);
+}
+
+#[test]
+fn test_every_proof_verifies() {
+    for count in 1..=9u8 {
+        let leaves: Vec<[u8; 32]> = (0..count).map(|i| [i; 32]).collect();
+        let root = merkle_root(&leaves);
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = merkle_proof(&leaves, index).unwrap();
+            assert!(verify_merkle_proof(*leaf, &proof, root), "{} of {}", index, count);
+        }
+        assert!(merkle_proof(&leaves, leaves.len()).is_none());
+    }
+}
+
+#[test]
+fn test_altered_proof_fails() {
+    let leaves: Vec<[u8; 32]> = (0..5).map(|i| [i; 32]).collect();
+    let root = merkle_root(&leaves);
+    let proof = merkle_proof(&leaves, 2).unwrap();
+
+    for step in 0..proof.len() {
+        let mut altered = proof.clone();
+        altered[step].0[0] ^= 1;
+        assert!(!verify_merkle_proof(leaves[2], &altered, root));
+
+        let mut flipped = proof.clone();
+        flipped[step].1 = !flipped[step].1;
+        assert!(!verify_merkle_proof(leaves[2], &flipped, root));
+    }
+    assert!(!verify_merkle_proof(leaves[3], &proof, root));
+}
@@ -0,0 +1,108 @@
+use crate::block::Block;
+use std::collections::HashMap;
+
+/// Storage for the blocks of a chain, indexed by height
+///
+/// `Chain` only talks to its blocks through this trait, so the in-memory
+/// default can be swapped for a persistent backend.
+pub trait BlockStore {
+    /// Returns the block at `height`, if any
+    fn get(&self, height: usize) -> Option<&Block>;
+
+    /// Appends a block at the next height
+    fn put(&mut self, block: Block);
+
+    /// Drops every block at height `len` and above
+    fn truncate(&mut self, len: usize);
+
+    /// Looks a block up by its hash
+    fn get_by_hash(&self, hash: &[u8; 32]) -> Option<&Block>;
+
+    /// Height of the block with `hash`. The default scans every block;
+    /// stores with a hash index should answer from it instead
+    fn height_of(&self, hash: &[u8; 32]) -> Option<usize> {
+        self.iter().position(|block| block.hash() == *hash)
+    }
+
+    fn len(&self) -> usize;
+
+    /// Iterates blocks from genesis to tip
+    fn iter(&self) -> Box<dyn Iterator<Item = &Block> + '_>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Releases spare memory; stores without in-memory buffers can ignore this
+    fn compact(&mut self) {}
+
+    fn last(&self) -> Option<&Block> {
+        self.len().checked_sub(1).and_then(|height| self.get(height))
+    }
+}
+
+/// Keeps every block in a `Vec`, with a hash index for lookups
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBlockStore {
+    blocks: Vec<Block>,
+    by_hash: HashMap<[u8; 32], usize>,
+}
+
+impl MemoryBlockStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(test)]
+    pub fn get_mut_for_testing(&mut self, height: usize) -> Option<&mut Block> {
+        self.blocks.get_mut(height)
+    }
+
+    #[cfg(test)]
+    pub fn clear_for_testing(&mut self) {
+        self.blocks.clear();
+        self.by_hash.clear();
+    }
+}
+
+impl BlockStore for MemoryBlockStore {
+    fn get(&self, height: usize) -> Option<&Block> {
+        self.blocks.get(height)
+    }
+
+    fn put(&mut self, block: Block) {
+        self.by_hash.insert(block.hash(), self.blocks.len());
+        self.blocks.push(block);
+    }
+
+    fn truncate(&mut self, len: usize) {
+        for block in self.blocks.iter().skip(len) {
+            self.by_hash.remove(&block.hash());
+        }
+        self.blocks.truncate(len);
+    }
+
+    fn get_by_hash(&self, hash: &[u8; 32]) -> Option<&Block> {
+        self.by_hash.get(hash).and_then(|&height| self.blocks.get(height))
+    }
+
+    fn height_of(&self, hash: &[u8; 32]) -> Option<usize> {
+        self.by_hash.get(hash).copied()
+    }
+
+    fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = &Block> + '_> {
+        Box::new(self.blocks.iter())
+    }
+
+    fn compact(&mut self) {
+        self.blocks.shrink_to_fit();
+        self.by_hash.shrink_to_fit();
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,93 @@
+use super::*;
+use crate::transaction::{Address, Transaction};
+
+fn create_test_block(nonce: u64, previous_hash: [u8; 32]) -> Block {
+    let transaction = Transaction::new(
+        Address::new([1; 20]),  // sender
+        Address::new([2; 20]),  // receiver
+        100,                    // amount
+        nonce,                  // unique nonce per block
+    );
+    Block::new(vec![transaction], previous_hash, 1).unwrap()
+}
+
+#[test]
+fn test_memory_store_starts_empty() {
+    let store = MemoryBlockStore::new();
+    assert!(store.is_empty());
+    assert!(store.last().is_none());
+    assert!(store.get(0).is_none());
+}
+
+#[test]
+fn test_memory_store_put_and_get() {
+    let mut store = MemoryBlockStore::new();
+    let first = create_test_block(0, [0; 32]);
+    let second = create_test_block(1, first.hash());
+
+    store.put(first.clone());
+    store.put(second.clone());
+
+    assert_eq!(store.len(), 2);
+    assert_eq!(store.get(0), Some(&first));
+    assert_eq!(store.get(1), Some(&second));
+    assert_eq!(store.last(), Some(&second));
+    assert_eq!(store.get_by_hash(&first.hash()), Some(&first));
+    assert_eq!(store.get_by_hash(&second.hash()), Some(&second));
+    assert!(store.get_by_hash(&[9; 32]).is_none());
+    assert_eq!(store.height_of(&second.hash()), Some(1));
+    assert!(store.height_of(&[9; 32]).is_none());
+}
+
+#[test]
+fn test_memory_store_iterates_in_height_order() {
+    let mut store = MemoryBlockStore::new();
+    let mut previous_hash = [0; 32];
+    for nonce in 0..4 {
+        let block = create_test_block(nonce, previous_hash);
+        previous_hash = block.hash();
+        store.put(block);
+    }
+
+    let hashes: Vec<_> = store.iter().map(|b| b.hash()).collect();
+    let indexed: Vec<_> = (0..store.len()).map(|h| store.get(h).unwrap().hash()).collect();
+    assert_eq!(hashes, indexed);
+}
+
+#[test]
+fn test_memory_store_compact_releases_capacity() {
+    let mut store = MemoryBlockStore::new();
+    let block = create_test_block(0, [0; 32]);
+    store.put(block.clone());
+    store.blocks.reserve(1000);
+    store.by_hash.reserve(1000);
+
+    store.compact();
+
+    assert!(store.blocks.capacity() < 1000);
+    assert!(store.by_hash.capacity() < 1000);
+    assert_eq!(store.get(0), Some(&block));
+    assert_eq!(store.get_by_hash(&block.hash()), Some(&block));
+}
+
+#[test]
+fn test_memory_store_truncate() {
+    let mut store = MemoryBlockStore::new();
+    let mut blocks = Vec::new();
+    let mut previous_hash = [0; 32];
+    for nonce in 0..4 {
+        let block = create_test_block(nonce, previous_hash);
+        previous_hash = block.hash();
+        blocks.push(block.clone());
+        store.put(block);
+    }
+
+    store.truncate(2);
+
+    assert_eq!(store.len(), 2);
+    assert_eq!(store.last(), Some(&blocks[1]));
+    assert!(store.get_by_hash(&blocks[1].hash()).is_some());
+    assert!(store.get_by_hash(&blocks[2].hash()).is_none());
+    assert!(store.get_by_hash(&blocks[3].hash()).is_none());
+    assert!(store.height_of(&blocks[2].hash()).is_none());
+}
@@ -1,6 +1,17 @@
+use crate::encoding::{encode_amount, encode_u64, from_hex, to_hex};
+use crate::hash::{ChainHasher, Hasher};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use k256::ecdsa::signature::hazmat::{PrehashSigner, PrehashVerifier};
+use k256::ecdsa::{Signature, SigningKey, VerifyingKey};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
 
+/// Most bytes `validate` accepts in a transaction's data payload
+pub const MAX_DATA_LEN: usize = 256;
+
 #[derive(Error, Debug)]
 pub enum TransactionError {
     #[error("Invalid amount: amount must be greater than 0")]
@@ -9,27 +20,102 @@ pub enum TransactionError {
     InvalidAddress,
     #[error("Sender and receiver cannot be the same")]
     SameSenderReceiver,
+    #[error("Amount is below the relay dust threshold")]
+    Dust,
+    #[error("Custom rule violated: {0}")]
+    CustomRule(String),
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Signature is not in low-S form")]
+    HighSSignature,
+    #[error("Data payload is {len} bytes, over the {max} byte limit")]
+    DataTooLarge { len: usize, max: usize },
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// An application-specific transaction check run on top of the built-in rules
+pub trait ValidationRule: Send + Sync {
+    /// Returns a description of the violation if `transaction` breaks the rule
+    fn check(&self, transaction: &Transaction) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Address([u8; 20]); // 20 bytes address like Ethereum
 
+// Addresses serialize as a bare lowercase hex string
+#[cfg(feature = "serde")]
+impl serde::Serialize for Address {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(&self.0))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Address {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let s = String::deserialize(deserializer)?;
+        from_hex(&s)
+            .map(Address)
+            .ok_or_else(|| D::Error::custom("expected 40 hex characters"))
+    }
+}
+
 impl Address {
     pub fn new(bytes: [u8; 20]) -> Self {
         Self(bytes)
     }
 
+    /// The all-zero address, reserved as the genesis sender
+    pub fn zero() -> Self {
+        Self([0; 20])
+    }
+
+    /// The address `key` signs for: the last 20 bytes of the SHA-256 of its
+    /// uncompressed point without the SEC1 tag byte, as Ethereum does with
+    /// Keccak-256
+    pub fn from_public_key(key: &VerifyingKey) -> Self {
+        let point = key.to_encoded_point(false);
+        let digest: [u8; 32] = Sha256::digest(&point.as_bytes()[1..]).into();
+        Self(digest[12..].try_into().unwrap())
+    }
+
+    /// Parses 40 hex characters, optionally prefixed with `0x`
+    pub fn from_hex(s: &str) -> Result<Self, TransactionError> {
+        from_hex(s.strip_prefix("0x").unwrap_or(s))
+            .map(Self)
+            .ok_or(TransactionError::InvalidAddress)
+    }
+
     pub fn as_bytes(&self) -> &[u8; 20] {
         &self.0
     }
 }
 
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", to_hex(&self.0))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Transaction {
     sender: Address,
     receiver: Address,
     amount: u64,
     nonce: u64, // To prevent replay attacks
+    // Optional random bytes that make otherwise-identical transactions (e.g.
+    // a retried payment) hash differently. The salt only affects identity:
+    // it is not a replay defence, the nonce remains the field that orders
+    // and de-duplicates a sender's spends, so a salted retry still conflicts
+    // with a pending transaction that has the same nonce.
+    salt: Option<[u8; 8]>,
+    // Opaque application bytes such as a memo or contract call; empty when unused
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    data: Vec<u8>,
+    signature: Option<Vec<u8>>,  // 64-byte r || s over `signing_hash()`, set by `sign`
+    public_key: Option<Vec<u8>>, // Compressed SEC1 key the sender address derives from
 }
 
 impl Transaction {
@@ -39,14 +125,91 @@ impl Transaction {
             receiver,
             amount,
             nonce,
+            salt: None,
+            data: Vec::new(),
+            signature: None,
+            public_key: None,
         }
     }
 
+    /// Attaches an explicit salt, changing the transaction's hash
+    pub fn with_salt(mut self, salt: [u8; 8]) -> Self {
+        self.salt = Some(salt);
+        self
+    }
+
+    /// Attaches a data payload, changing the transaction's hash
+    pub fn with_data(mut self, data: Vec<u8>) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Attaches a salt drawn from a cryptographically secure RNG
+    #[cfg(feature = "std")]
+    pub fn with_random_salt(self) -> Self {
+        self.with_salt(rand::random())
+    }
+
+    /// Signs the signing hash and attaches the signature and public key.
+    /// Changing any hashed field afterwards invalidates the signature
+    pub fn sign(&mut self, private_key: &SigningKey) {
+        let signature: Signature = private_key
+            .sign_prehash(&self.signing_hash())
+            .expect("a 32-byte prehash is always signable");
+        let signature = signature.normalize_s().unwrap_or(signature);
+        self.signature = Some(signature.to_bytes().to_vec());
+        self.public_key = Some(
+            private_key
+                .verifying_key()
+                .to_encoded_point(true)
+                .as_bytes()
+                .to_vec(),
+        );
+    }
+
+    /// Checks the attached signature covers this transaction's hash and was
+    /// made by the key the sender address derives from. Only the low-S form
+    /// is accepted: negating S gives a second valid signature for the same
+    /// key and message, which would let anyone restate a signed transaction
+    pub fn verify_signature(&self) -> Result<(), TransactionError> {
+        let (Some(signature), Some(public_key)) = (&self.signature, &self.public_key) else {
+            return Err(TransactionError::InvalidSignature);
+        };
+
+        let key = VerifyingKey::from_sec1_bytes(public_key)
+            .map_err(|_| TransactionError::InvalidSignature)?;
+        if Address::from_public_key(&key) != self.sender {
+            return Err(TransactionError::InvalidSignature);
+        }
+
+        let signature =
+            Signature::from_slice(signature).map_err(|_| TransactionError::InvalidSignature)?;
+        if signature.normalize_s().is_some() {
+            return Err(TransactionError::HighSSignature);
+        }
+        key.verify_prehash(&self.signing_hash(), &signature)
+            .map_err(|_| TransactionError::InvalidSignature)
+    }
+
+    /// Checks the consensus rules: a block containing a transaction that
+    /// fails here is invalid. Unsigned transactions are still accepted; a
+    /// signed one must carry a valid signature
     pub fn validate(&self, is_genesis: bool) -> Result<(), TransactionError> {
         if self.amount == 0 {
             return Err(TransactionError::InvalidAmount);
         }
 
+        self.check_data_len(MAX_DATA_LEN)?;
+
+        if self.signature.is_some() || self.public_key.is_some() {
+            self.verify_signature()?;
+        }
+
+        // Only genesis may spend from the reserved zero address
+        if !is_genesis && self.sender == Address::zero() {
+            return Err(TransactionError::InvalidAddress);
+        }
+
         if !is_genesis && self.sender == self.receiver {
             return Err(TransactionError::SameSenderReceiver);
         }
@@ -54,6 +217,49 @@ impl Transaction {
         Ok(())
     }
 
+    /// Checks local relay policy, which is stricter than consensus. A node
+    /// may refuse to relay a transaction that fails here, but must still
+    /// accept it inside a peer's otherwise valid block
+    pub fn validate_policy(&self, dust_threshold: u64) -> Result<(), TransactionError> {
+        if self.amount < dust_threshold {
+            return Err(TransactionError::Dust);
+        }
+
+        Ok(())
+    }
+
+    /// Checks the data payload holds at most `max` bytes
+    pub fn check_data_len(&self, max: usize) -> Result<(), TransactionError> {
+        if self.data.len() > max {
+            return Err(TransactionError::DataTooLarge {
+                len: self.data.len(),
+                max,
+            });
+        }
+        Ok(())
+    }
+
+    /// Runs the built-in checks, then each extra rule in order
+    pub fn validate_with_rules(
+        &self,
+        is_genesis: bool,
+        rules: &[Arc<dyn ValidationRule>],
+    ) -> Result<(), TransactionError> {
+        self.validate(is_genesis)?;
+
+        for rule in rules {
+            rule.check(self).map_err(TransactionError::CustomRule)?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether this mints new funds: a coinbase is sent from the zero
+    /// address, which holds no outputs to spend
+    pub fn is_coinbase(&self) -> bool {
+        self.sender == Address::zero()
+    }
+
     pub fn sender(&self) -> &Address {
         &self.sender
     }
@@ -70,13 +276,68 @@ impl Transaction {
         self.nonce
     }
 
+    pub fn salt(&self) -> Option<&[u8; 8]> {
+        self.salt.as_ref()
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn signature(&self) -> Option<&[u8]> {
+        self.signature.as_deref()
+    }
+
+    pub fn public_key(&self) -> Option<&[u8]> {
+        self.public_key.as_deref()
+    }
+
+    /// Reattaches a stored signature and public key without checking them
+    pub fn with_signature(mut self, signature: Vec<u8>, public_key: Vec<u8>) -> Self {
+        self.signature = Some(signature);
+        self.public_key = Some(public_key);
+        self
+    }
+
+    /// What `sign` signs: every field except the signature and public key
+    pub fn signing_hash(&self) -> [u8; 32] {
+        ChainHasher::hash(&self.signing_bytes())
+    }
+
+    /// Identifies the transaction. Covers the signature and public key too,
+    /// so a block committing to these hashes commits to its signatures; an
+    /// unsigned transaction's hash equals its signing hash
     pub fn hash(&self) -> [u8; 32] {
-        let mut hasher = Sha256::new();
-        hasher.update(self.sender.as_bytes());
-        hasher.update(self.receiver.as_bytes());
-        hasher.update(self.amount.to_be_bytes());
-        hasher.update(self.nonce.to_be_bytes());
-        hasher.finalize().into()
+        let mut bytes = self.signing_bytes();
+        if let Some(signature) = &self.signature {
+            bytes.extend_from_slice(signature);
+        }
+        if let Some(public_key) = &self.public_key {
+            bytes.extend_from_slice(public_key);
+        }
+        ChainHasher::hash(&bytes)
+    }
+
+    /// `hash()` as lowercase hex
+    pub fn hash_hex(&self) -> String {
+        to_hex(&self.hash())
+    }
+
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(20 + 20 + 8 + 8 + 8 + 32);
+        bytes.extend_from_slice(self.sender.as_bytes());
+        bytes.extend_from_slice(self.receiver.as_bytes());
+        bytes.extend_from_slice(&encode_amount(self.amount));
+        bytes.extend_from_slice(&encode_u64(self.nonce));
+        if let Some(salt) = &self.salt {
+            bytes.extend_from_slice(salt);
+        }
+        // Through its hash, so each combination of salt and data gives a
+        // distinct input length and none can pass for another
+        if !self.data.is_empty() {
+            bytes.extend_from_slice(&ChainHasher::hash(&self.data));
+        }
+        bytes
     }
 }
 
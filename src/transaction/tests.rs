@@ -1,4 +1,5 @@
 use super::*;
+use k256::ecdsa::{Signature, SigningKey};
 
 fn create_test_address(value: u8) -> Address {
     let bytes = [value; 20];
@@ -74,6 +75,25 @@ fn test_address_equality() {
     assert_ne!(address1, address3);
 }
 
+#[test]
+fn test_address_orders_by_bytes() {
+    let mut high = [0; 20];
+    high[0] = 1;
+    let mut low = [0xff; 20];
+    low[0] = 0;
+    let mut addresses = vec![Address::new(high), Address::zero(), Address::new(low)];
+    addresses.sort();
+    assert_eq!(addresses, [Address::zero(), Address::new(low), Address::new(high)]);
+
+    let balances: std::collections::BTreeMap<Address, u64> =
+        addresses.iter().cloned().zip([1, 2, 3]).collect();
+    assert_eq!(balances.keys().next(), Some(&Address::zero()));
+    assert_eq!(balances[&Address::new(high)], 3);
+
+    let hashed: std::collections::HashMap<Address, u64> = balances.into_iter().collect();
+    assert_eq!(hashed[&Address::new(low)], 2);
+}
+
 #[test]
 fn test_transaction_uniqueness() {
     let tx1 = create_test_transaction();
@@ -85,4 +105,200 @@ fn test_transaction_uniqueness() {
     );
 
     assert_ne!(tx1, tx2);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_dust_fails_policy_but_not_consensus() {
+    let dust = Transaction::new(create_test_address(1), create_test_address(2), 5, 1);
+
+    assert!(dust.validate(false).is_ok());
+    assert!(matches!(dust.validate_policy(10), Err(TransactionError::Dust)));
+    assert!(dust.validate_policy(5).is_ok());
+}
+
+#[test]
+fn test_salt_distinguishes_identical_transactions() {
+    let unsalted = create_test_transaction();
+    let first = create_test_transaction().with_salt([1; 8]);
+    let second = create_test_transaction().with_salt([2; 8]);
+
+    assert_ne!(first.hash(), second.hash());
+    assert_ne!(first.hash(), unsalted.hash());
+    assert_eq!(first.nonce(), second.nonce());
+    assert_eq!(first.salt(), Some(&[1; 8]));
+    assert_eq!(unsalted.salt(), None);
+}
+
+#[test]
+fn test_data_distinguishes_identical_transactions() {
+    let plain = create_test_transaction();
+    let first = create_test_transaction().with_data(b"memo".to_vec());
+    let second = create_test_transaction().with_data(b"call".to_vec());
+
+    assert_ne!(first.hash(), second.hash());
+    assert_ne!(first.hash(), plain.hash());
+    assert_ne!(first.signing_hash(), plain.signing_hash());
+    assert_eq!(first.data(), b"memo");
+    assert!(plain.data().is_empty());
+}
+
+#[test]
+fn test_data_length_limit() {
+    let at_limit = create_test_transaction().with_data(vec![0; MAX_DATA_LEN]);
+    assert!(at_limit.validate(false).is_ok());
+
+    let over = create_test_transaction().with_data(vec![0; MAX_DATA_LEN + 1]);
+    assert!(matches!(
+        over.validate(false),
+        Err(TransactionError::DataTooLarge { len, max }) if len == MAX_DATA_LEN + 1 && max == MAX_DATA_LEN
+    ));
+    assert!(matches!(at_limit.check_data_len(8), Err(TransactionError::DataTooLarge { .. })));
+}
+
+#[test]
+fn test_random_salts_differ() {
+    let first = create_test_transaction().with_random_salt();
+    let second = create_test_transaction().with_random_salt();
+
+    assert!(first.salt().is_some());
+    assert_ne!(first.hash(), second.hash());
+}
+
+#[test]
+fn test_zero_sender_only_allowed_in_genesis() {
+    let transaction = Transaction::new(Address::zero(), create_test_address(2), 100, 1);
+
+    assert!(matches!(
+        transaction.validate(false),
+        Err(TransactionError::InvalidAddress)
+    ));
+    assert!(transaction.validate(true).is_ok());
+}
+
+fn create_test_key(value: u8) -> SigningKey {
+    SigningKey::from_slice(&[value; 32]).unwrap()
+}
+
+fn create_signed_transaction(key: &SigningKey) -> Transaction {
+    let sender = Address::from_public_key(key.verifying_key());
+    let mut transaction = Transaction::new(
+        sender,                  // sender owns the key
+        create_test_address(2),  // receiver
+        100,                     // amount
+        1,                       // nonce
+    );
+    transaction.sign(key);
+    transaction
+}
+
+#[test]
+fn test_signed_transaction_validates() {
+    let transaction = create_signed_transaction(&create_test_key(7));
+
+    assert_eq!(transaction.signature().map(<[u8]>::len), Some(64));
+    assert_eq!(transaction.public_key().map(<[u8]>::len), Some(33));
+    assert!(transaction.verify_signature().is_ok());
+    assert!(transaction.validate(false).is_ok());
+}
+
+#[test]
+fn test_sender_matches_derived_address() {
+    let key = create_test_key(9);
+    let address = Address::from_public_key(key.verifying_key());
+    let mut transaction = Transaction::new(address.clone(), create_test_address(2), 100, 1);
+    transaction.sign(&key);
+
+    assert_eq!(transaction.sender(), &address);
+    assert!(transaction.validate(false).is_ok());
+
+    // Deterministic, and distinct keys give distinct addresses
+    assert_eq!(Address::from_public_key(key.verifying_key()), address);
+    assert_ne!(Address::from_public_key(create_test_key(10).verifying_key()), address);
+}
+
+#[test]
+fn test_tampered_amount_fails_signature() {
+    let mut transaction = create_signed_transaction(&create_test_key(7));
+    transaction.amount = 1_000_000;
+
+    assert!(matches!(transaction.validate(false), Err(TransactionError::InvalidSignature)));
+}
+
+#[test]
+fn test_signature_must_match_sender() {
+    // Valid signature, but by a key the sender address does not derive from
+    let mut transaction = create_test_transaction();
+    transaction.sign(&create_test_key(7));
+    assert!(matches!(transaction.validate(false), Err(TransactionError::InvalidSignature)));
+
+    // Swapping in another key's public key breaks the signature itself
+    let mut transaction = create_signed_transaction(&create_test_key(7));
+    let other = create_signed_transaction(&create_test_key(8));
+    transaction.public_key = other.public_key.clone();
+    transaction.sender = other.sender.clone();
+    assert!(matches!(transaction.validate(false), Err(TransactionError::InvalidSignature)));
+}
+
+#[test]
+fn test_sign_produces_low_s() {
+    for value in 1..=20 {
+        let transaction = create_signed_transaction(&create_test_key(value));
+        let signature = Signature::from_slice(transaction.signature().unwrap()).unwrap();
+        assert!(signature.normalize_s().is_none(), "high S for key {}", value);
+    }
+}
+
+#[test]
+fn test_high_s_signature_rejected() {
+    let mut transaction = create_signed_transaction(&create_test_key(7));
+    let signature = Signature::from_slice(transaction.signature().unwrap()).unwrap();
+    let (r, s) = signature.split_scalars();
+    let high = Signature::from_scalars(r, -s).unwrap();
+    transaction.signature = Some(high.to_bytes().to_vec());
+
+    assert!(matches!(transaction.verify_signature(), Err(TransactionError::HighSSignature)));
+    assert!(matches!(transaction.validate(false), Err(TransactionError::HighSSignature)));
+}
+
+#[test]
+fn test_unsigned_transaction_still_valid() {
+    let transaction = create_test_transaction();
+
+    assert!(transaction.signature().is_none());
+    assert!(transaction.validate(false).is_ok());
+    assert!(matches!(transaction.verify_signature(), Err(TransactionError::InvalidSignature)));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_address_serializes_as_hex() {
+    let address = Address::new([0xab; 20]);
+    let json = serde_json::to_string(&address).unwrap();
+    assert_eq!(json, format!("\"{}\"", "ab".repeat(20)));
+    assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+
+    assert!(serde_json::from_str::<Address>("\"abcd\"").is_err());
+}
+
+#[test]
+fn test_address_hex_round_trip() {
+    let address = Address::new([0xab; 20]);
+    let rendered = address.to_string();
+    assert_eq!(rendered, format!("0x{}", "ab".repeat(20)));
+
+    assert_eq!(Address::from_hex(&rendered).unwrap(), address);
+    assert_eq!(Address::from_hex(&rendered[2..]).unwrap(), address);
+    assert_eq!(Address::from_hex(&rendered.to_uppercase()[2..]).unwrap(), address);
+
+    for bad in ["", "0x", "0xab", &"zz".repeat(20), &"ab".repeat(21)] {
+        assert!(matches!(Address::from_hex(bad), Err(TransactionError::InvalidAddress)));
+    }
+}
+
+#[test]
+fn test_hash_hex_matches_hash() {
+    let transaction = create_test_transaction();
+    let hex = transaction.hash_hex();
+    assert_eq!(hex.len(), 64);
+    assert_eq!(crate::encoding::from_hex(&hex), Some(transaction.hash()));
+}
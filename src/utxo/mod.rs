@@ -1,7 +1,11 @@
-use crate::transaction::Address;
+use crate::hash::{ChainHasher, Hasher};
+use crate::transaction::{Address, Transaction};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UTXO {
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hash"))]
     utxo_id: [u8; 32],  // Unique identifier for this UTXO
     owner: Address,     // Who can spend this UTXO
     amount: u64,        // How much it's worth
@@ -46,6 +50,143 @@ impl UTXO {
   pub fn tx_index(&self) -> u32 {
       self.tx_index
   }
+
+  /// Number of blocks since this UTXO was created
+  pub fn age(&self, current_height: u64) -> u64 {
+      current_height.saturating_sub(self.block_created)
+  }
+}
+
+/// The set of unspent outputs, keyed by UTXO id
+#[derive(Debug, Clone, Default)]
+pub struct UtxoSet {
+    utxos: HashMap<[u8; 32], UTXO>,
+    burned: u64, // Value removed by dust pruning, no longer spendable
+}
+
+impl UtxoSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, utxo: UTXO) {
+        self.utxos.insert(*utxo.utxo_id(), utxo);
+    }
+
+    /// Removes and returns the UTXO, if it is unspent
+    pub fn remove(&mut self, utxo_id: &[u8; 32]) -> Option<UTXO> {
+        self.utxos.remove(utxo_id)
+    }
+
+    pub fn get(&self, utxo_id: &[u8; 32]) -> Option<&UTXO> {
+        self.utxos.get(utxo_id)
+    }
+
+    /// Age of an unspent output at `current_height`, or `None` if unknown
+    pub fn coin_age(&self, utxo_id: &[u8; 32], current_height: u64) -> Option<u64> {
+        self.get(utxo_id).map(|utxo| utxo.age(current_height))
+    }
+
+    /// Drops outputs worth less than `threshold` that are at least `min_age`
+    /// blocks old and returns their total value, which is counted as burned.
+    /// Pruned outputs can no longer be spent, so owners' balances shrink.
+    /// Totals saturate at `u64::MAX`
+    pub fn prune_dust(&mut self, threshold: u64, current_height: u64, min_age: u64) -> u64 {
+        let mut pruned = 0u64;
+        self.utxos.retain(|_, utxo| {
+            let is_dust = utxo.amount() < threshold && utxo.age(current_height) >= min_age;
+            if is_dust {
+                pruned = pruned.saturating_add(utxo.amount());
+            }
+            !is_dust
+        });
+        self.burned = self.burned.saturating_add(pruned);
+        pruned
+    }
+
+    /// Applies `transaction`, found at `tx_index` of the block at `height`.
+    /// The sender's outputs are spent oldest first until they cover the
+    /// amount, the receiver gets a new output and any excess returns to the
+    /// sender as change. The zero address (genesis) spends nothing. A sender
+    /// that cannot cover the amount has everything spent and the receiver is
+    /// still credited in full; overspends must be rejected before this, as
+    /// `Chain` does for every block it accepts
+    pub fn apply_transaction(&mut self, transaction: &Transaction, height: u64, tx_index: u32) {
+        let sender = transaction.sender();
+        let mut spent = 0u64;
+        if *sender != Address::zero() {
+            let mut owned: Vec<&UTXO> = self
+                .utxos
+                .values()
+                .filter(|utxo| utxo.owner() == sender)
+                .collect();
+            owned.sort_unstable_by_key(|utxo| {
+                (utxo.block_created(), utxo.tx_index(), *utxo.utxo_id())
+            });
+
+            let inputs: Vec<[u8; 32]> = owned
+                .into_iter()
+                .take_while(|utxo| {
+                    let needed = spent < transaction.amount();
+                    if needed {
+                        spent = spent.saturating_add(utxo.amount());
+                    }
+                    needed
+                })
+                .map(|utxo| *utxo.utxo_id())
+                .collect();
+            for id in &inputs {
+                self.utxos.remove(id);
+            }
+        }
+
+        let output_id = |index: u8| -> [u8; 32] {
+            let mut input = transaction.hash().to_vec();
+            input.extend_from_slice(&height.to_be_bytes());
+            input.extend_from_slice(&tx_index.to_be_bytes());
+            input.push(index);
+            ChainHasher::hash(&input)
+        };
+        let receiver = transaction.receiver().clone();
+        self.insert(UTXO::new(output_id(0), receiver, transaction.amount(), height, tx_index));
+        if spent > transaction.amount() {
+            let change = spent - transaction.amount();
+            self.insert(UTXO::new(output_id(1), sender.clone(), change, height, tx_index));
+        }
+    }
+
+    /// Total value of the unspent outputs `owner` holds
+    pub fn balance_of(&self, owner: &Address) -> u64 {
+        self.utxos
+            .values()
+            .filter(|utxo| utxo.owner() == owner)
+            .fold(0, |total, utxo| total.saturating_add(utxo.amount()))
+    }
+
+    /// Total value held in unspent outputs, saturating at `u64::MAX`
+    pub fn total_value(&self) -> u64 {
+        self.utxos
+            .values()
+            .fold(0, |total, utxo| total.saturating_add(utxo.amount()))
+    }
+
+    /// Total value ever removed by `prune_dust`
+    pub fn burned(&self) -> u64 {
+        self.burned
+    }
+
+    /// Releases capacity left over from outputs that have since been spent
+    pub fn shrink_to_fit(&mut self) {
+        self.utxos.shrink_to_fit();
+    }
+
+    pub fn len(&self) -> usize {
+        self.utxos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.utxos.is_empty()
+    }
 }
 
 #[cfg(test)]
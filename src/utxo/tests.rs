@@ -1,4 +1,5 @@
 use super::*;
+use crate::transaction::Transaction;
 
 fn create_test_address() -> Address {
    Address::new([1; 20])
@@ -30,3 +31,100 @@ fn test_utxo_creation() {
    assert_eq!(utxo.block_created(), block_created);
    assert_eq!(utxo.tx_index(), tx_index);
 }
+
+#[test]
+fn test_utxo_age() {
+   let utxo = UTXO::new(create_test_utxo_id(), create_test_address(), 100, 5, 0);
+
+   assert_eq!(utxo.age(5), 0);
+   assert_eq!(utxo.age(12), 7);
+   assert_eq!(utxo.age(3), 0); // Heights before creation saturate
+}
+
+#[test]
+fn test_utxo_set_coin_age() {
+   let mut set = UtxoSet::new();
+   set.insert(UTXO::new([1; 32], create_test_address(), 100, 0, 0));
+   set.insert(UTXO::new([2; 32], create_test_address(), 50, 4, 1));
+
+   assert_eq!(set.len(), 2);
+   assert_eq!(set.coin_age(&[1; 32], 10), Some(10));
+   assert_eq!(set.coin_age(&[2; 32], 10), Some(6));
+   assert_eq!(set.coin_age(&[3; 32], 10), None);
+
+   set.remove(&[1; 32]);
+   assert_eq!(set.coin_age(&[1; 32], 10), None);
+}
+
+#[test]
+fn test_prune_dust_burns_old_small_outputs() {
+   let mut set = UtxoSet::new();
+   set.insert(UTXO::new([1; 32], create_test_address(), 3, 0, 0));   // old dust
+   set.insert(UTXO::new([2; 32], create_test_address(), 2, 1, 0));   // old dust
+   set.insert(UTXO::new([3; 32], create_test_address(), 4, 95, 0));  // recent dust
+   set.insert(UTXO::new([4; 32], create_test_address(), 500, 0, 0)); // not dust
+   let supply_before = set.total_value();
+
+   let pruned = set.prune_dust(5, 100, 10);
+
+   assert_eq!(pruned, 5);
+   assert_eq!(set.burned(), 5);
+   assert_eq!(set.len(), 2);
+   assert!(set.get(&[1; 32]).is_none());
+   assert!(set.get(&[2; 32]).is_none());
+   assert!(set.get(&[3; 32]).is_some());
+   assert_eq!(set.total_value() + set.burned(), supply_before);
+}
+
+#[test]
+fn test_totals_saturate() {
+   let mut set = UtxoSet::new();
+   set.insert(UTXO::new([1; 32], create_test_address(), u64::MAX, 0, 0));
+   set.insert(UTXO::new([2; 32], create_test_address(), u64::MAX - 1, 0, 0));
+   assert_eq!(set.total_value(), u64::MAX);
+   assert_eq!(set.balance_of(&create_test_address()), u64::MAX);
+
+   set.insert(UTXO::new([3; 32], create_test_address(), 2, 0, 0));
+   assert_eq!(set.prune_dust(u64::MAX, 100, 10), u64::MAX);
+   set.insert(UTXO::new([4; 32], create_test_address(), 2, 0, 0));
+   assert_eq!(set.prune_dust(u64::MAX, 100, 10), 2);
+   assert_eq!(set.burned(), u64::MAX);
+}
+
+#[test]
+fn test_apply_transaction_spends_and_returns_change() {
+   let alice = Address::new([1; 20]);
+   let bob = Address::new([2; 20]);
+   let mut set = UtxoSet::new();
+
+   set.apply_transaction(&Transaction::new(Address::zero(), alice.clone(), 100, 0), 0, 0);
+   assert_eq!(set.balance_of(&alice), 100);
+
+   set.apply_transaction(&Transaction::new(alice.clone(), bob.clone(), 30, 1), 1, 0);
+   assert_eq!(set.balance_of(&alice), 70);
+   assert_eq!(set.balance_of(&bob), 30);
+   assert_eq!(set.len(), 2); // bob's output and alice's change
+   assert_eq!(set.total_value(), 100);
+
+   // Spending exactly an output leaves no change
+   set.apply_transaction(&Transaction::new(bob.clone(), alice.clone(), 30, 2), 2, 0);
+   assert_eq!(set.balance_of(&bob), 0);
+   assert_eq!(set.balance_of(&alice), 100);
+   assert_eq!(set.len(), 2);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_utxo_serde_round_trip() {
+   let utxo = UTXO::new(create_test_utxo_id(), create_test_address(), 100, 3, 1);
+
+   let json = serde_json::to_string(&utxo).unwrap();
+   assert!(json.contains(&"01".repeat(32)));
+
+   let decoded: UTXO = serde_json::from_str(&json).unwrap();
+   assert_eq!(decoded.utxo_id(), utxo.utxo_id());
+   assert_eq!(decoded.owner(), utxo.owner());
+   assert_eq!(decoded.amount(), utxo.amount());
+   assert_eq!(decoded.block_created(), utxo.block_created());
+   assert_eq!(decoded.tx_index(), utxo.tx_index());
+}
@@ -0,0 +1,51 @@
+//! Signing identities: a secp256k1 key and the address it controls.
+
+use crate::transaction::{Address, Transaction};
+use core::fmt;
+use k256::ecdsa::{SigningKey, VerifyingKey};
+
+pub struct Wallet {
+    key: SigningKey,
+    address: Address, // Derived from `key` once, as every transaction needs it
+}
+
+impl Wallet {
+    /// A wallet with a fresh key from the operating system's RNG
+    #[cfg(feature = "std")]
+    pub fn generate() -> Self {
+        Self::from_signing_key(SigningKey::random(&mut rand::rngs::OsRng))
+    }
+
+    pub fn from_signing_key(key: SigningKey) -> Self {
+        let address = Address::from_public_key(key.verifying_key());
+        Self { key, address }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address.clone()
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    /// Builds a payment from this wallet's address and signs it. Transactions
+    /// carry no fees, so there is nothing to set beyond amount and nonce
+    pub fn create_transaction(&self, to: Address, amount: u64, nonce: u64) -> Transaction {
+        let mut transaction = Transaction::new(self.address(), to, amount, nonce);
+        transaction.sign(&self.key);
+        transaction
+    }
+}
+
+// Never print the private key
+impl fmt::Debug for Wallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Wallet")
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests;
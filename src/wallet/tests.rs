@@ -0,0 +1,27 @@
+use super::*;
+
+#[test]
+fn test_created_transaction_validates_against_wallet_address() {
+    let wallet = Wallet::generate();
+    let tx = wallet.create_transaction(Address::new([2; 20]), 100, 0);
+
+    assert_eq!(tx.sender(), &wallet.address());
+    assert_eq!(Address::from_public_key(wallet.verifying_key()), wallet.address());
+    assert!(tx.verify_signature().is_ok());
+    assert!(tx.validate(false).is_ok());
+}
+
+#[test]
+fn test_generated_wallets_differ() {
+    assert_ne!(Wallet::generate().address(), Wallet::generate().address());
+}
+
+#[test]
+fn test_same_key_same_address() {
+    let key = SigningKey::from_slice(&[7; 32]).unwrap();
+    let first = Wallet::from_signing_key(key.clone());
+    let second = Wallet::from_signing_key(key);
+
+    assert_eq!(first.address(), second.address());
+    assert!(!format!("{:?}", first).contains("key"));
+}
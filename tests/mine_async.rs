@@ -0,0 +1,29 @@
+#![cfg(feature = "tokio")]
+
+use dhai_chain::block::Block;
+use dhai_chain::transaction::{Address, Transaction};
+use std::time::Duration;
+
+fn create_test_block(difficulty: u32) -> Block {
+    let transaction = Transaction::new(Address::new([1; 20]), Address::new([2; 20]), 100, 0);
+    Block::new(vec![transaction], [0; 32], difficulty).unwrap()
+}
+
+#[tokio::test]
+async fn test_mine_async_seals_block() {
+    let mut block = create_test_block(8);
+    block.mine_async().await;
+
+    assert!(block.is_sealed());
+    assert!(block.verify(false).is_ok());
+}
+
+#[tokio::test]
+async fn test_dropped_mine_async_leaves_block_unsealed() {
+    // Far beyond what can be found before the timeout
+    let mut block = create_test_block(64);
+    let result = tokio::time::timeout(Duration::from_millis(50), block.mine_async()).await;
+
+    assert!(result.is_err());
+    assert!(!block.is_sealed());
+}